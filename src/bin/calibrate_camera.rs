@@ -0,0 +1,33 @@
+use std::env;
+
+use rust_drone_follow::utils::camera_calibration::calibrate_from_video;
+use rust_drone_follow::utils::camera_model_file::write_file;
+
+/// Calibrates a camera from a video of a chessboard, and saves the result in a file that can be
+/// read by camera_model_file::read_file.
+///
+/// Usage: calibrate_camera video_file columns rows square_size output_file [frame_step]
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    if args.len() < 6 {
+        eprintln!("Usage: {} video_file columns rows square_size output_file [frame_step]", args[0]);
+        eprintln!("columns and rows are the number of inner corners of the chessboard.");
+        std::process::exit(1);
+    }
+    let columns = args[2].parse::<i32>().expect("columns should be an integer");
+    let rows = args[3].parse::<i32>().expect("rows should be an integer");
+    let square_size = args[4].parse::<f64>().expect("square_size should be a number");
+    let frame_step = args.get(6).map(|s| s.parse::<usize>().expect("frame_step should be an integer")).unwrap_or(15);
+
+    match calibrate_from_video(&args[1], (columns, rows), square_size, frame_step) {
+        Ok(camera_model) => {
+            write_file(&args[5], &camera_model);
+            println!("fx: {} fy: {} cx: {} cy: {}", camera_model.fx, camera_model.fy, camera_model.cx, camera_model.cy);
+            println!("distortion: {:?}", camera_model.distortion);
+        }
+        Err(e) => {
+            eprintln!("Calibration failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::env;
+use std::net::UdpSocket;
+
+use rust_drone_follow::models::telemetry_record::TelemetryRecord;
+
+/// Width and height of the plot in characters.
+const PLOT_WIDTH: i32 = 60;
+const PLOT_HEIGHT: i32 = 20;
+
+/// Receives the telemetry sent by a HatFollower (stream_telemetry in the settings) and prints every
+/// record as a line of JSON, or with --plot draws the detected (o) and estimated (x) position of
+/// the hat on the terminal.
+///
+/// Usage: telemetry_listen address:port [--plot video_width video_height]
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    if args.len() < 2 || (args.len() > 2 && (args[2] != "--plot" || args.len() < 5)) {
+        eprintln!("Usage: {} address:port [--plot video_width video_height]", args[0]);
+        std::process::exit(1);
+    }
+    let plot_size = if args.len() > 2 {
+        Some((parse_video_size(&args[3], "video_width"), parse_video_size(&args[4], "video_height")))
+    } else {
+        None
+    };
+
+    let socket = UdpSocket::bind(&args[1]).expect("Couldn't listen on the given address");
+    let mut buffer = [0; 65536];
+    loop {
+        let size = match socket.recv(&mut buffer) {
+            Ok(size) => size,
+            Err(e) => {
+                eprintln!("Couldn't receive: {}", e);
+                continue;
+            }
+        };
+        let bytes = &buffer[..size];
+        match (TelemetryRecord::from_bytes(bytes), plot_size) {
+            (Some(record), Some(plot_size)) => plot(&record, plot_size),
+            (Some(record), None) => println!("{}", record.to_json()),
+            // Plotting needs the binary format, JSON is printed as it arrived.
+            (None, _) => println!("{}", String::from_utf8_lossy(bytes)),
+        }
+    }
+}
+
+/// Parses a dimension of the video, and exits if it isn't a positive integer.
+fn parse_video_size(value: &str, name: &str) -> i32 {
+    match value.parse::<i32>() {
+        Ok(size) if size > 0 => size,
+        _ => {
+            eprintln!("{} should be a positive integer: {}", name, value);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn plot(record: &TelemetryRecord, (width, height): (i32, i32)) {
+    let to_cell = |x: i32, y: i32| (
+        ((x + width / 2) * PLOT_WIDTH / width).min(PLOT_WIDTH - 1).max(0),
+        ((height / 2 - y) * PLOT_HEIGHT / height).min(PLOT_HEIGHT - 1).max(0)
+    );
+    let detection = record.detection.as_ref().map(|p| to_cell(p.x, p.y));
+    let estimate = record.estimate.as_ref().map(|p| to_cell(p.x, p.y));
+
+    // Clears the terminal and moves the cursor to the top left corner.
+    let mut screen = String::from("\x1b[2J\x1b[H");
+    screen.push_str(&format!("+{}+\n", "-".repeat(PLOT_WIDTH as usize)));
+    for row in 0..PLOT_HEIGHT {
+        screen.push('|');
+        for column in 0..PLOT_WIDTH {
+            screen.push(if estimate == Some((column, row)) {
+                'x'
+            } else if detection == Some((column, row)) {
+                'o'
+            } else if column == PLOT_WIDTH / 2 && row == PLOT_HEIGHT / 2 {
+                '+'
+            } else {
+                ' '
+            });
+        }
+        screen.push_str("|\n");
+    }
+    screen.push_str(&format!("+{}+\n", "-".repeat(PLOT_WIDTH as usize)));
+    let (lr, bf, du, turn) = record.command;
+    screen.push_str(&format!("frame: {} time: {:.2} state: {:?} certainty: {:.2}\n", record.frame_num, record.timestamp, record.state, record.estimation_certainty));
+    screen.push_str(&format!("command: {:.2} {:.2} {:.2} {:.2} battery: {}\n", lr, bf, du, turn,
+                             record.battery.map(|b| format!("{:.0}%", b * 100.0)).unwrap_or_else(|| String::from("-"))));
+    print!("{}", screen);
+}
@@ -0,0 +1,351 @@
+use std::time::Instant;
+
+use crate::traits::Controller;
+
+use crate::models::frame::Frame;
+use crate::models::geofence::Geofence;
+use crate::models::telemetry::Telemetry;
+
+/// The speed (as a command value) with which the drone flies back when it left the geofence.
+const RETURN_SPEED: f64 = 0.3;
+/// How much the command flying the drone back has to change to be sent again.
+const RETURN_COMMAND_CHANGE: f64 = 0.01;
+
+/// What the GeofenceController does when the drone left the geofence.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BreachAction {
+    /// Lands the drone where it is, and ignores every move command until the next takeoff.
+    Land,
+    /// Flies the drone towards the takeoff point (and down under the maximum altitude) until it is
+    /// inside the geofence again, then stops it and gives back the control. It doesn't fly all the
+    /// way back to the takeoff point.
+    ReturnHome,
+}
+
+/// A controller that wraps any other Controller and keeps the drone inside a geofence.
+///
+/// The position of the drone is estimated by integrating the commanded velocities (using the
+/// maximum speed and turn rate of the wrapped controller). If the telemetry contains the yaw or
+/// the altitude, those are used instead of the integrated values.
+///
+/// Commands that would push the drone outwards within `margin` metres of the border (or above the
+/// maximum altitude) have their outward component removed. If the drone still leaves the geofence,
+/// the BreachAction is executed.
+pub struct GeofenceController<C: Controller> {
+    controller: C,
+    geofence: Geofence,
+    margin: f64,
+    action: BreachAction,
+    /// Estimated position in metres and heading in radians (counter-clockwise), relative to the
+    /// takeoff point.
+    x: f64,
+    y: f64,
+    altitude: f64,
+    heading: f64,
+    yaw_at_takeoff: Option<f64>,
+    command: (f64, f64, f64, f64),
+    last_update: Instant,
+    breached: bool,
+}
+
+impl<C: Controller> GeofenceController<C> {
+    /// Wraps the given controller, and keeps the drone inside the geofence.
+    ///
+    /// Usage:
+    /// ```
+    /// use rust_drone_follow::controllers::mock_controller::MockController;
+    /// use rust_drone_follow::controllers::geofence_controller::{GeofenceController, BreachAction};
+    /// use rust_drone_follow::models::geofence::{Geofence, GeofenceShape};
+    /// // ...
+    /// # fn main() {
+    ///     let geofence = Geofence::new(GeofenceShape::Circle(20.0), 5.0);
+    ///     let controller = GeofenceController::new(MockController::new(), geofence, 2.0, BreachAction::ReturnHome);
+    /// # }
+    /// ```
+    pub fn new(controller: C, geofence: Geofence, margin: f64, action: BreachAction) -> GeofenceController<C> {
+        GeofenceController {
+            controller,
+            geofence,
+            margin,
+            action,
+            x: 0.0,
+            y: 0.0,
+            altitude: 0.0,
+            heading: 0.0,
+            yaw_at_takeoff: None,
+            command: (0.0, 0.0, 0.0, 0.0),
+            last_update: Instant::now(),
+            breached: false,
+        }
+    }
+
+    /// Returns the estimated position (x, y, altitude) of the drone in metres, relative to the
+    /// takeoff point.
+    pub fn get_position(&self) -> (f64, f64, f64) {
+        (self.x, self.y, self.altitude)
+    }
+
+    /// Returns true while the drone is outside the geofence (or landed because of it).
+    pub fn is_breached(&self) -> bool {
+        self.breached
+    }
+
+    /// Returns the wrapped controller.
+    pub fn get_inner(&self) -> &C {
+        &self.controller
+    }
+
+    /// Moves the estimated position with the last command for the time passed since the last
+    /// update.
+    fn update_position(&mut self) {
+        let dt = self.last_update.elapsed().as_secs_f64();
+        self.last_update = Instant::now();
+        let telemetry = self.controller.get_telemetry();
+        let max_speed = self.controller.get_max_speed();
+        let (lr, bf, du, turn) = self.command;
+
+        self.heading = match (telemetry.yaw, self.yaw_at_takeoff) {
+            (Some(yaw), Some(yaw_at_takeoff)) => yaw - yaw_at_takeoff,
+            // A positive turn command turns right, which is clockwise.
+            _ => self.heading - turn * self.controller.get_max_turn_rate() * dt,
+        };
+        let (wx, wy) = self.body_to_world(lr * max_speed, bf * max_speed);
+        self.x += wx * dt;
+        self.y += wy * dt;
+        self.altitude = match telemetry.altitude {
+            Some(altitude) => altitude,
+            None => self.altitude + du * max_speed * dt,
+        };
+    }
+
+    fn body_to_world(&self, left_right: f64, back_front: f64) -> (f64, f64) {
+        let (sin, cos) = self.heading.sin_cos();
+        (left_right * cos - back_front * sin, left_right * sin + back_front * cos)
+    }
+
+    fn world_to_body(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.heading.sin_cos();
+        (x * cos + y * sin, -x * sin + y * cos)
+    }
+
+    /// Removes the parts of the command that would push the drone out of the geofence near the
+    /// border.
+    fn limit_command(&self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) -> (f64, f64, f64, f64) {
+        let (mut wx, mut wy) = self.body_to_world(left_right, back_front);
+        let (distance, (nx, ny)) = self.geofence.distance_to_border(self.x, self.y);
+        if distance < self.margin || !self.geofence.contains(self.x, self.y) {
+            let outward = wx * nx + wy * ny;
+            if outward > 0.0 {
+                wx -= outward * nx;
+                wy -= outward * ny;
+            }
+        }
+        let (left_right, back_front) = self.world_to_body(wx, wy);
+        let down_up = if self.altitude >= self.geofence.max_altitude - self.margin { down_up.min(0.0) } else { down_up };
+
+        (left_right, back_front, down_up, turn_left_right)
+    }
+
+    fn is_outside(&self) -> bool {
+        !self.geofence.contains(self.x, self.y) || self.altitude > self.geofence.max_altitude
+    }
+
+    fn send(&mut self, command: (f64, f64, f64, f64)) {
+        self.controller.move_all(command.0, command.1, command.2, command.3);
+        self.command = command;
+    }
+
+    /// Executes the BreachAction if the drone is outside the geofence. Returns true if the control
+    /// is taken over from the caller.
+    fn handle_breach(&mut self) -> bool {
+        if self.breached && self.action == BreachAction::Land {
+            return true;
+        }
+        if !self.is_outside() {
+            if self.breached {
+                self.breached = false;
+                self.controller.stop();
+                self.command = (0.0, 0.0, 0.0, 0.0);
+            }
+            return false;
+        }
+
+        self.breached = true;
+        match self.action {
+            BreachAction::Land => {
+                self.controller.stop();
+                self.controller.land();
+                self.command = (0.0, 0.0, 0.0, 0.0);
+            }
+            BreachAction::ReturnHome => {
+                let d = (self.x.powi(2) + self.y.powi(2)).sqrt();
+                let (wx, wy) = if self.geofence.contains(self.x, self.y) || d == 0.0 {
+                    (0.0, 0.0)
+                } else {
+                    (-self.x / d * RETURN_SPEED, -self.y / d * RETURN_SPEED)
+                };
+                let (lr, bf) = self.world_to_body(wx, wy);
+                let du = if self.altitude > self.geofence.max_altitude { -RETURN_SPEED } else { 0.0 };
+                // It is called on every tick, so the command is only sent again if it changed.
+                let (c_lr, c_bf, c_du, c_turn) = self.command;
+                let change = (lr - c_lr).abs().max((bf - c_bf).abs()).max((du - c_du).abs()).max(c_turn.abs());
+                if change > RETURN_COMMAND_CHANGE {
+                    self.send((lr, bf, du, 0.0));
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<C: Controller> Controller for GeofenceController<C> {
+    fn init(&mut self) {
+        self.controller.init();
+    }
+    fn shutdown(&mut self) {
+        self.controller.shutdown();
+    }
+
+    /// Also resets the estimated position, the takeoff point is the origin of the geofence.
+    fn takeoff(&mut self) {
+        self.controller.takeoff();
+        self.x = 0.0;
+        self.y = 0.0;
+        self.heading = 0.0;
+        self.altitude = 0.0;
+        self.yaw_at_takeoff = self.controller.get_telemetry().yaw;
+        self.command = (0.0, 0.0, 0.0, 0.0);
+        self.last_update = Instant::now();
+        self.breached = false;
+    }
+    fn land(&mut self) {
+        self.update_position();
+        self.controller.land();
+        self.command = (0.0, 0.0, 0.0, 0.0);
+    }
+
+    fn move_all(&mut self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) {
+        self.update_position();
+        if self.handle_breach() {
+            return;
+        }
+        let command = self.limit_command(left_right, back_front, down_up, turn_left_right);
+        self.send(command);
+    }
+
+    fn stop(&mut self) {
+        self.update_position();
+        self.controller.stop();
+        self.command = (0.0, 0.0, 0.0, 0.0);
+    }
+
+    fn frame_received(&mut self, frame: &Frame) {
+        self.controller.frame_received(frame);
+    }
+
+    /// Updates the estimated position, and checks whether the drone left the geofence.
+    fn tick(&mut self) {
+        self.controller.tick();
+        self.update_position();
+        self.handle_breach();
+    }
+
+    fn get_kv(&self) -> f64 {
+        self.controller.get_kv()
+    }
+
+    fn get_ka(&self) -> f64 {
+        self.controller.get_ka()
+    }
+
+    fn get_telemetry(&self) -> Telemetry {
+        self.controller.get_telemetry()
+    }
+
+    fn get_max_speed(&self) -> f64 {
+        self.controller.get_max_speed()
+    }
+
+    fn get_max_turn_rate(&self) -> f64 {
+        self.controller.get_max_turn_rate()
+    }
+
+    fn convert_metric(&self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) -> (f64, f64, f64, f64) {
+        self.controller.convert_metric(left_right, back_front, down_up, turn_left_right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::traits::Controller;
+
+    use crate::controllers::recording_controller::RecordingController;
+
+    use crate::models::geofence::{Geofence, GeofenceShape};
+
+    use super::{GeofenceController, BreachAction};
+
+    fn geofence_controller(action: BreachAction) -> GeofenceController<RecordingController> {
+        let geofence = Geofence::new(GeofenceShape::Circle(10.0), 5.0);
+        GeofenceController::new(RecordingController::new(), geofence, 1.0, action)
+    }
+
+    #[test]
+    fn outward_command_near_border_should_be_clamped() {
+        let mut sut = geofence_controller(BreachAction::Land);
+        sut.y = 9.5;
+        sut.move_all(0.5, 0.5, 0.0, 0.0);
+        let (lr, bf, _, _) = sut.get_inner().moves[0];
+
+        assert!((lr - 0.5).abs() < 1e-3 && bf.abs() < 1e-3)
+    }
+
+    #[test]
+    fn inward_command_near_border_should_not_be_changed() {
+        let mut sut = geofence_controller(BreachAction::Land);
+        sut.y = 9.5;
+        sut.move_all(0.0, -0.5, 0.0, 0.0);
+
+        assert!((sut.get_inner().moves[0].1 + 0.5).abs() < 1e-3)
+    }
+
+    #[test]
+    fn breach_should_land() {
+        let mut sut = geofence_controller(BreachAction::Land);
+        sut.x = 12.0;
+        sut.move_all(0.0, 0.5, 0.0, 0.0);
+
+        assert!(sut.get_inner().landed && sut.get_inner().moves.is_empty() && sut.is_breached())
+    }
+
+    #[test]
+    fn breach_should_return_home() {
+        let mut sut = geofence_controller(BreachAction::ReturnHome);
+        sut.x = 12.0;
+        sut.move_all(0.5, 0.0, 0.0, 0.0);
+
+        assert!(sut.get_inner().moves[0].0 < 0.0 && !sut.get_inner().landed)
+    }
+
+    #[test]
+    fn return_home_should_not_be_sent_again_on_every_tick() {
+        let mut sut = geofence_controller(BreachAction::ReturnHome);
+        sut.x = 12.0;
+        sut.tick();
+        sut.tick();
+        sut.tick();
+
+        assert_eq!(sut.get_inner().moves.len(), 1)
+    }
+
+    #[test]
+    fn control_should_be_given_back_inside_the_geofence() {
+        let mut sut = geofence_controller(BreachAction::ReturnHome);
+        sut.x = 12.0;
+        sut.tick();
+        sut.x = 9.0;
+        sut.move_all(0.0, 0.5, 0.0, 0.0);
+
+        assert!(!sut.is_breached() && sut.get_inner().stopped && sut.get_inner().moves.last().unwrap().1 > 0.0)
+    }
+}
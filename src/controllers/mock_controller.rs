@@ -1,16 +1,10 @@
-use opencv::core::Mat;
-use opencv::videoio::{VideoCapture, VideoCaptureTrait, CAP_ANY};
-
 use crate::traits::Controller;
 
-/// The MockController acts as a false controller that provides a video file to the MainFrame along
-/// with it's resolution, and does nothing on commands given to it.
+/// The MockController acts as a false controller that does nothing on commands given to it.
 ///
-/// You can use it to test the tracking system on a prerecorded video.
+/// You can use it together with a VideoFileSource to test the tracking system on a prerecorded
+/// video.
 pub struct MockController {
-    video: VideoCapture,
-    height: usize,
-    width: usize,
 }
 
 impl MockController {
@@ -19,14 +13,11 @@ impl MockController {
     /// use rust_drone_follow::controllers::mock_controller::MockController;
     /// // ...
     /// # fn main() {
-    ///     let controller = MockController::new("video_file.mp4", 640, 368);
+    ///     let controller = MockController::new();
     /// # }
     /// ```
-    pub fn new(filename: &str, width: usize, height: usize) -> MockController {
+    pub fn new() -> MockController {
         MockController {
-            video: VideoCapture::from_file(filename, CAP_ANY).unwrap(),
-            height,
-            width
         }
     }
 }
@@ -49,20 +40,6 @@ impl Controller for MockController {
     fn stop(&mut self) {
     }
 
-    fn get_video_height(&self) -> usize {
-        self.height
-    }
-
-    fn get_video_width(&self) -> usize {
-        self.width
-    }
-
-
-    /// Should return current image from drone
-    fn get_next_frame(&mut self, img: &mut Mat) -> opencv::Result<bool> {
-        self.video.read(img)
-    }
-
     /// Conversion rate between pixels/dt and drone speed which is in (-1.0, 1.0), where dt is the
     /// time difference between frames
     fn get_kv(&self) -> f64 {
@@ -1 +1,7 @@
+pub mod geofence_controller;
 pub mod mock_controller;
+pub mod replay_controller;
+pub mod safe_controller;
+
+#[cfg(test)]
+pub(crate) mod recording_controller;
@@ -0,0 +1,47 @@
+use crate::traits::Controller;
+
+use crate::models::telemetry::Telemetry;
+
+/// A controller used by the tests, which records the commands given to it.
+pub struct RecordingController {
+    pub moves: Vec<(f64, f64, f64, f64)>,
+    pub landed: bool,
+    pub stopped: bool,
+    /// The battery level reported in the telemetry.
+    pub battery: Option<f64>,
+}
+
+impl RecordingController {
+    pub fn new() -> RecordingController {
+        RecordingController {
+            moves: Vec::new(),
+            landed: false,
+            stopped: false,
+            battery: None,
+        }
+    }
+}
+
+impl Controller for RecordingController {
+    fn init(&mut self) {}
+    fn shutdown(&mut self) {}
+    fn takeoff(&mut self) {
+        self.landed = false;
+    }
+    fn land(&mut self) {
+        self.landed = true;
+    }
+    fn move_all(&mut self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) {
+        self.moves.push((left_right, back_front, down_up, turn_left_right));
+    }
+    fn stop(&mut self) {
+        self.stopped = true;
+    }
+    fn get_kv(&self) -> f64 { 1.0 }
+    fn get_ka(&self) -> f64 { 1.0 }
+    fn get_telemetry(&self) -> Telemetry {
+        let mut telemetry = Telemetry::new();
+        telemetry.battery = self.battery;
+        telemetry
+    }
+}
@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use opencv::core::{MatTrait, StsObjectNotFound, StsOutOfRange};
+use opencv::imgcodecs::{imread, IMREAD_COLOR};
+
+use crate::traits::{Controller, FrameSource};
+
+use crate::models::frame::Frame;
+use crate::models::recorded_frame::RecordedFrame;
+use crate::models::telemetry::Telemetry;
+
+use crate::utils::session_recorder::{read_manifest, read_frames};
+
+/// Replays a session saved by the SessionRecorder together with its ReplaySource: the source gives
+/// back the raw frames with their original timestamps, and the controller reports the telemetry
+/// that was recorded with the frame being processed (found by its timestamp in frame_received),
+/// so a HatFollower run on them gets exactly the same input as the original flight, even if the
+/// frames are read ahead in pipelined mode. The conversion rates and maximum speeds of the original
+/// controller are also read from the session. Like the MockController, it does nothing on
+/// commands given to it.
+///
+/// The recorded outputs can be used to compare a run with a modified detector or filter
+/// frame-by-frame against the original flight.
+pub struct ReplayController {
+    directory: PathBuf,
+    width: usize,
+    height: usize,
+    kv: f64,
+    ka: f64,
+    max_speed: f64,
+    max_turn_rate: f64,
+    frames: Arc<Vec<RecordedFrame>>,
+    /// Index of the frame being processed.
+    current: Option<usize>,
+}
+
+/// Gives out the frames of a session replayed by a ReplayController.
+pub struct ReplaySource {
+    directory: PathBuf,
+    width: usize,
+    height: usize,
+    frames: Arc<Vec<RecordedFrame>>,
+    next: usize,
+}
+
+/// Returns the index of the frame recorded closest to the given time.
+fn find_frame(frames: &[RecordedFrame], timestamp: f64) -> Option<usize> {
+    let after = frames.partition_point(|recorded| recorded.timestamp < timestamp);
+    let before = after.checked_sub(1);
+    let after = Some(after).filter(|i| *i < frames.len());
+    match (before, after) {
+        (Some(b), Some(a)) if timestamp - frames[b].timestamp < frames[a].timestamp - timestamp => Some(b),
+        (b, None) => b,
+        (_, a) => a,
+    }
+}
+
+impl ReplayController {
+    /// Reads the session from the given directory, and panics if it can't be read (see open).
+    ///
+    /// Usage:
+    /// ```no_run
+    /// use rust_drone_follow::controllers::replay_controller::ReplayController;
+    /// // ...
+    /// # fn main() {
+    ///     let controller = ReplayController::new("sessions/flight_1");
+    ///     let source = controller.get_source();
+    /// # }
+    /// ```
+    pub fn new(directory: &str) -> ReplayController {
+        ReplayController::open(directory).unwrap_or_else(|e| panic!("Couldn't read the session {}: {}", directory, e))
+    }
+
+    /// Reads the session from the given directory. Returns an error if its manifest or its frames
+    /// can't be read.
+    pub fn open(directory: &str) -> std::io::Result<ReplayController> {
+        let manifest = read_manifest(directory)?;
+        let frames = read_frames(directory)?;
+        Ok(ReplayController {
+            directory: PathBuf::from(directory),
+            width: manifest.video_width,
+            height: manifest.video_height,
+            kv: manifest.kv,
+            ka: manifest.ka,
+            max_speed: manifest.max_speed,
+            max_turn_rate: manifest.max_turn_rate,
+            frames: Arc::new(frames),
+            current: None,
+        })
+    }
+
+    /// Returns the source of the recorded frames, starting from the first one.
+    pub fn get_source(&self) -> ReplaySource {
+        ReplaySource {
+            directory: self.directory.clone(),
+            width: self.width,
+            height: self.height,
+            frames: Arc::clone(&self.frames),
+            next: 0,
+        }
+    }
+
+    /// Returns every recorded frame, with the output of the original flight.
+    pub fn get_recorded_frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    /// Returns the recorded frame that is being processed.
+    pub fn get_current_frame(&self) -> Option<&RecordedFrame> {
+        self.current.and_then(|i| self.frames.get(i))
+    }
+}
+
+impl Controller for ReplayController {
+    fn init(&mut self) {
+    }
+    fn shutdown(&mut self) {
+    }
+
+    fn takeoff(&mut self) {
+    }
+    fn land(&mut self) {
+    }
+
+    fn move_all(&mut self, _left_right: f64, _back_front: f64, _down_up: f64, _turn_left_right: f64) {
+    }
+
+    fn stop(&mut self) {
+    }
+
+    /// Looks up the recorded frame by the timestamp of the frame.
+    fn frame_received(&mut self, frame: &Frame) {
+        self.current = find_frame(&self.frames, frame.timestamp);
+    }
+
+    fn get_kv(&self) -> f64 {
+        self.kv
+    }
+
+    fn get_ka(&self) -> f64 {
+        self.ka
+    }
+
+    /// Returns the telemetry recorded with the current frame.
+    fn get_telemetry(&self) -> Telemetry {
+        self.get_current_frame()
+            .map(|recorded| recorded.telemetry.clone())
+            .unwrap_or_else(Telemetry::new)
+    }
+
+    fn get_max_speed(&self) -> f64 {
+        self.max_speed
+    }
+
+    fn get_max_turn_rate(&self) -> f64 {
+        self.max_turn_rate
+    }
+}
+
+impl FrameSource for ReplaySource {
+    fn get_video_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_video_width(&self) -> usize {
+        self.width
+    }
+
+    /// Loads the next raw image of the session, with its original timestamp. Returns an error if
+    /// the image is missing or can't be read.
+    fn get_next_frame(&mut self, frame: &mut Frame) -> opencv::Result<bool> {
+        let recorded = match self.frames.get(self.next) {
+            Some(recorded) => recorded,
+            None => return Ok(false),
+        };
+        let path = self.directory.join(&recorded.image_file);
+        frame.image = imread(&path.to_string_lossy(), IMREAD_COLOR)?;
+        if frame.image.empty()? {
+            return Err(opencv::Error::new(StsObjectNotFound, format!("Couldn't read the image {}", path.display())));
+        }
+        frame.timestamp = recorded.timestamp;
+        self.next += 1;
+        Ok(true)
+    }
+
+    fn get_position(&self) -> Option<usize> {
+        Some(self.next)
+    }
+
+    fn seek(&mut self, frame_num: usize) -> opencv::Result<bool> {
+        if frame_num >= self.frames.len() {
+            return Err(opencv::Error::new(StsOutOfRange, format!("The session has no frame {}", frame_num)));
+        }
+        self.next = frame_num;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::recorded_frame::RecordedFrame;
+
+    use super::find_frame;
+
+    fn recorded_frame(timestamp: f64) -> RecordedFrame {
+        let line = format!("1 0.1 {} frames/1.png - - - 0 - - 0 0 0 0 - - - - - - - - -", timestamp);
+        RecordedFrame::from_line(&line).unwrap()
+    }
+
+    #[test]
+    fn closest_frame_should_be_found() {
+        let frames = vec![recorded_frame(1.0), recorded_frame(1.1), recorded_frame(1.2)];
+
+        assert_eq!(find_frame(&frames, 1.1), Some(1));
+        assert_eq!(find_frame(&frames, 1.16), Some(2));
+        assert_eq!(find_frame(&frames, 0.5), Some(0));
+        assert_eq!(find_frame(&frames, 3.0), Some(2));
+        assert_eq!(find_frame(&[], 1.0), None)
+    }
+}
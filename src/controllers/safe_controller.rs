@@ -0,0 +1,404 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::traits::Controller;
+
+use crate::models::frame::Frame;
+use crate::models::telemetry::Telemetry;
+
+use crate::utils::text_exporter::TextExporter;
+
+/// The longest time (in seconds) the slew limit is applied for at once, so a command given after an
+/// idle period is still approached gradually.
+const MAX_SLEW_INTERVAL: f64 = 0.1;
+/// How many of the last interventions are kept.
+const MAX_INTERVENTIONS: usize = 1000;
+
+/// The limits enforced by a SafeController.
+pub struct SafetyLimits {
+    /// Maximum absolute value of the left_right, back_front and down_up commands.
+    pub max_speed: f64,
+    /// Maximum absolute value of the turn_left_right command.
+    pub max_turn: f64,
+    /// Maximum change of a command value per second (slew limit). f64::INFINITY turns the slew
+    /// limit off.
+    pub max_change_per_second: f64,
+    /// The drone lands if the battery level reported in the telemetry (between 0.0 and 1.0) goes
+    /// under this value.
+    pub min_battery: f64,
+    /// The drone lands if there was no new frame for this many milliseconds.
+    pub max_video_loss_ms: u128,
+    /// If it is set, every intervention is also saved into this file.
+    pub log_file: Option<String>,
+}
+
+impl SafetyLimits {
+    /// Returns limits that are safe in most situations: (half speed, half turn rate, 1.0 change per
+    /// second, land under 15% battery or after 1 second without video, no log file)
+    pub fn new() -> SafetyLimits {
+        SafetyLimits {
+            max_speed: 0.5,
+            max_turn: 0.5,
+            max_change_per_second: 1.0,
+            min_battery: 0.15,
+            max_video_loss_ms: 1000,
+            log_file: None,
+        }
+    }
+}
+
+/// Describes a case when the SafeController had to change or replace a command.
+pub enum Intervention {
+    /// A command was over the maximum speed or turn rate (values requested).
+    Saturated(f64, f64, f64, f64),
+    /// A command changed too fast, so it was approached gradually (values requested).
+    SlewLimited(f64, f64, f64, f64),
+    /// The drone was landed because of low battery (battery level).
+    LowBattery(f64),
+    /// The drone was landed because there was no video (milliseconds since the last frame).
+    VideoLost(u128),
+    /// The drone was landed because the emergency stop flag was set.
+    EmergencyStop,
+}
+
+impl Intervention {
+    /// Returns a human readable description of the intervention.
+    pub fn describe(&self) -> String {
+        match self {
+            Intervention::Saturated(lr, bf, du, t) => format!("saturated command: {} {} {} {}", lr, bf, du, t),
+            Intervention::SlewLimited(lr, bf, du, t) => format!("slew limited command: {} {} {} {}", lr, bf, du, t),
+            Intervention::LowBattery(level) => format!("landing because of low battery: {}", level),
+            Intervention::VideoLost(ms) => format!("landing because of no video for {} ms", ms),
+            Intervention::EmergencyStop => String::from("landing because of emergency stop"),
+        }
+    }
+}
+
+/// A controller that wraps any other Controller and makes sure that it can't be given dangerous
+/// commands. It limits the speed and turn rate, changes commands gradually, lands the drone on low
+/// battery, lost video or emergency stop, and logs every time it intervened.
+///
+/// After it landed the drone, it ignores every move command until the next takeoff (which is also
+/// ignored while the emergency stop flag is set).
+pub struct SafeController<C: Controller> {
+    controller: C,
+    limits: SafetyLimits,
+    emergency_stop: Arc<AtomicBool>,
+    /// The last command requested from the drone and the last one that was actually sent.
+    requested: (f64, f64, f64, f64),
+    sent: (f64, f64, f64, f64),
+    last_sent_at: Instant,
+    last_frame_at: Instant,
+    landed: bool,
+    interventions: VecDeque<Intervention>,
+    intervention_count: usize,
+    text_exporter: Option<TextExporter>,
+}
+
+impl<C: Controller> SafeController<C> {
+    /// Wraps the given controller, and enforces the given limits on it.
+    ///
+    /// Usage:
+    /// ```
+    /// use rust_drone_follow::controllers::mock_controller::MockController;
+    /// use rust_drone_follow::controllers::safe_controller::{SafeController, SafetyLimits};
+    /// // ...
+    /// # fn main() {
+    ///     let controller = SafeController::new(MockController::new(), SafetyLimits::new());
+    ///     let emergency_stop = controller.get_emergency_stop_flag();
+    /// # }
+    /// ```
+    pub fn new(controller: C, limits: SafetyLimits) -> SafeController<C> {
+        let text_exporter = limits.log_file.as_ref().map(|_| TextExporter::new());
+        SafeController {
+            controller,
+            limits,
+            emergency_stop: Arc::new(AtomicBool::new(false)),
+            requested: (0.0, 0.0, 0.0, 0.0),
+            sent: (0.0, 0.0, 0.0, 0.0),
+            last_sent_at: Instant::now(),
+            last_frame_at: Instant::now(),
+            landed: false,
+            interventions: VecDeque::new(),
+            intervention_count: 0,
+            text_exporter,
+        }
+    }
+
+    /// Returns the flag which makes the drone land (and stay landed) when it is set to true. It can
+    /// be set from any thread.
+    pub fn get_emergency_stop_flag(&self) -> Arc<AtomicBool> {
+        self.emergency_stop.clone()
+    }
+
+    /// Returns the last interventions (at most 1000, the older ones are only counted).
+    pub fn get_interventions(&self) -> &VecDeque<Intervention> {
+        &self.interventions
+    }
+
+    /// Returns the number of interventions made since the creation of the controller.
+    pub fn get_intervention_count(&self) -> usize {
+        self.intervention_count
+    }
+
+    /// Returns the wrapped controller.
+    pub fn get_inner(&self) -> &C {
+        &self.controller
+    }
+
+    fn log(&mut self, intervention: Intervention) {
+        if let (Some(text_exporter), Some(filename)) = (&mut self.text_exporter, &self.limits.log_file) {
+            text_exporter.save_row(filename.as_str(), format!("{}\n", intervention.describe()));
+        }
+        if self.interventions.len() >= MAX_INTERVENTIONS {
+            self.interventions.pop_front();
+        }
+        self.interventions.push_back(intervention);
+        self.intervention_count += 1;
+    }
+
+    fn emergency_land(&mut self, reason: Intervention) {
+        self.log(reason);
+        self.controller.stop();
+        self.controller.land();
+        self.landed = true;
+        self.requested = (0.0, 0.0, 0.0, 0.0);
+        self.sent = (0.0, 0.0, 0.0, 0.0);
+    }
+
+    /// Lands the drone if any of the conditions for it is met. Returns true if the drone is (or was
+    /// already) landed.
+    fn check_landing_conditions(&mut self) -> bool {
+        if self.landed {
+            return true;
+        }
+        if self.emergency_stop.load(Ordering::SeqCst) {
+            self.emergency_land(Intervention::EmergencyStop);
+        } else if let Some(battery) = self.controller.get_telemetry().battery {
+            if battery < self.limits.min_battery {
+                self.emergency_land(Intervention::LowBattery(battery));
+            }
+        }
+        if !self.landed {
+            let video_loss = self.last_frame_at.elapsed().as_millis();
+            if video_loss > self.limits.max_video_loss_ms {
+                self.emergency_land(Intervention::VideoLost(video_loss));
+            }
+        }
+        self.landed
+    }
+
+    /// Sends the requested command to the drone, changing each value at most by the slew limit.
+    fn send_towards_requested(&mut self) {
+        let max_step = if self.limits.max_change_per_second.is_infinite() {
+            f64::INFINITY
+        } else {
+            let elapsed = self.last_sent_at.elapsed().as_secs_f64().min(MAX_SLEW_INTERVAL);
+            self.limits.max_change_per_second * elapsed
+        };
+        let step = |from: f64, to: f64| from + (to - from).min(max_step).max(-max_step);
+        let (lr, bf, du, t) = self.requested;
+        let (s_lr, s_bf, s_du, s_t) = self.sent;
+        let new = (step(s_lr, lr), step(s_bf, bf), step(s_du, du), step(s_t, t));
+
+        if new != self.requested {
+            self.log(Intervention::SlewLimited(lr, bf, du, t));
+        }
+        if new != self.sent {
+            self.controller.move_all(new.0, new.1, new.2, new.3);
+            self.sent = new;
+        }
+        self.last_sent_at = Instant::now();
+    }
+}
+
+impl<C: Controller> Controller for SafeController<C> {
+    fn init(&mut self) {
+        self.controller.init();
+        self.last_frame_at = Instant::now();
+    }
+    fn shutdown(&mut self) {
+        self.controller.shutdown();
+    }
+
+    fn takeoff(&mut self) {
+        if self.emergency_stop.load(Ordering::SeqCst) {
+            self.log(Intervention::EmergencyStop);
+            return;
+        }
+        self.landed = false;
+        self.last_frame_at = Instant::now();
+        self.controller.takeoff();
+    }
+    fn land(&mut self) {
+        self.controller.land();
+        self.landed = true;
+    }
+
+    /// Clamps the command to the limits, and starts approaching it within the slew limit.
+    fn move_all(&mut self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) {
+        if self.check_landing_conditions() {
+            return;
+        }
+        let max_speed = self.limits.max_speed;
+        let max_turn = self.limits.max_turn;
+        let clamped = (
+            left_right.min(max_speed).max(-max_speed),
+            back_front.min(max_speed).max(-max_speed),
+            down_up.min(max_speed).max(-max_speed),
+            turn_left_right.min(max_turn).max(-max_turn)
+        );
+        if clamped != (left_right, back_front, down_up, turn_left_right) {
+            self.log(Intervention::Saturated(left_right, back_front, down_up, turn_left_right));
+        }
+
+        self.requested = clamped;
+        self.send_towards_requested();
+    }
+
+    fn stop(&mut self) {
+        self.controller.stop();
+        self.requested = (0.0, 0.0, 0.0, 0.0);
+        self.sent = (0.0, 0.0, 0.0, 0.0);
+        self.last_sent_at = Instant::now();
+    }
+
+    /// Checks whether the video was lost before this frame, and restarts the timer of the video loss.
+    fn frame_received(&mut self, frame: &Frame) {
+        self.controller.frame_received(frame);
+        self.check_landing_conditions();
+        self.last_frame_at = Instant::now();
+    }
+
+    /// Keeps approaching the last requested command, and checks whether the drone needs to land.
+    fn tick(&mut self) {
+        self.controller.tick();
+        if !self.check_landing_conditions() && self.requested != self.sent {
+            self.send_towards_requested();
+        }
+    }
+
+    fn get_kv(&self) -> f64 {
+        self.controller.get_kv()
+    }
+
+    fn get_ka(&self) -> f64 {
+        self.controller.get_ka()
+    }
+
+    fn get_telemetry(&self) -> Telemetry {
+        self.controller.get_telemetry()
+    }
+
+    fn get_max_speed(&self) -> f64 {
+        self.controller.get_max_speed()
+    }
+
+    fn get_max_turn_rate(&self) -> f64 {
+        self.controller.get_max_turn_rate()
+    }
+
+    fn convert_metric(&self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) -> (f64, f64, f64, f64) {
+        self.controller.convert_metric(left_right, back_front, down_up, turn_left_right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::traits::Controller;
+
+    use opencv::core::Mat;
+
+    use crate::controllers::recording_controller::RecordingController;
+
+    use crate::models::frame::Frame;
+
+    use super::{SafeController, SafetyLimits, Intervention};
+
+    fn safe_controller(battery: Option<f64>, max_change_per_second: f64) -> SafeController<RecordingController> {
+        let mut limits = SafetyLimits::new();
+        limits.max_change_per_second = max_change_per_second;
+        let mut controller = RecordingController::new();
+        controller.battery = battery;
+        SafeController::new(controller, limits)
+    }
+
+    #[test]
+    fn commands_should_be_saturated() {
+        let mut sut = safe_controller(None, f64::INFINITY);
+        sut.move_all(1.0, -1.0, 0.0, 1.0);
+
+        assert!(sut.get_inner().moves[0] == (0.5, -0.5, 0.0, 0.5))
+    }
+
+    #[test]
+    fn sudden_change_should_be_slew_limited() {
+        let mut sut = safe_controller(None, 1.0);
+        sut.move_all(0.5, 0.0, 0.0, 0.0);
+
+        assert!(sut.get_inner().moves.iter().all(|m| m.0 < 0.5))
+    }
+
+    #[test]
+    fn change_after_idle_period_should_be_slew_limited() {
+        let mut sut = safe_controller(None, 1.0);
+        thread::sleep(Duration::from_millis(600));
+        sut.move_all(0.5, 0.0, 0.0, 0.0);
+
+        assert!(sut.get_inner().moves.iter().all(|m| m.0 <= 0.1 + 1e-9))
+    }
+
+    #[test]
+    fn only_the_last_interventions_should_be_kept() {
+        let mut sut = safe_controller(None, f64::INFINITY);
+        for _ in 0..1500 {
+            sut.move_all(1.0, 0.0, 0.0, 0.0);
+        }
+
+        assert_eq!(sut.get_interventions().len(), 1000);
+        assert!(sut.get_intervention_count() >= 1500)
+    }
+
+    #[test]
+    fn stopped_frames_should_land() {
+        let mut limits = SafetyLimits::new();
+        limits.max_video_loss_ms = 50;
+        let mut sut = SafeController::new(RecordingController::new(), limits);
+        sut.takeoff();
+        sut.frame_received(&Frame { image: Mat::default().unwrap(), timestamp: 0.0 });
+        sut.tick();
+        assert!(!sut.get_inner().landed);
+
+        thread::sleep(Duration::from_millis(100));
+        sut.tick();
+
+        assert!(sut.get_inner().landed);
+        assert!(matches!(sut.get_interventions()[0], Intervention::VideoLost(_)))
+    }
+
+    #[test]
+    fn low_battery_should_land() {
+        let mut sut = safe_controller(Some(0.05), f64::INFINITY);
+        sut.move_all(0.1, 0.0, 0.0, 0.0);
+
+        assert!(sut.get_inner().landed && sut.get_inner().moves.is_empty())
+    }
+
+    #[test]
+    fn emergency_stop_should_land_and_block_commands() {
+        let mut sut = safe_controller(None, f64::INFINITY);
+        sut.get_emergency_stop_flag().store(true, Ordering::SeqCst);
+        sut.move_all(0.1, 0.0, 0.0, 0.0);
+        sut.takeoff();
+        sut.move_all(0.1, 0.0, 0.0, 0.0);
+
+        assert!(sut.get_inner().landed && sut.get_inner().moves.is_empty());
+        assert!(matches!(sut.get_interventions()[0], Intervention::EmergencyStop))
+    }
+}
@@ -1,3 +1,5 @@
 pub mod naive_detector;
+pub mod optical_flow_detector;
 
-pub use naive_detector::NaiveDetector;
\ No newline at end of file
+pub use naive_detector::NaiveDetector;
+pub use optical_flow_detector::OpticalFlowDetector;
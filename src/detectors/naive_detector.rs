@@ -28,9 +28,12 @@ enum TanableAngle {
 ///
 /// This angle will always be between -pi/2 and pi/2.
 ///
-/// This Detector doesn't take into account previous coordinates of the tracked object.
+/// This Detector doesn't take into account previous coordinates of the tracked object, unless it
+/// was given a target with switch_target. In that case it follows the fitting object closest to
+/// the target, until it loses it.
 pub struct NaiveDetector {
     point: Option<GeometricPoint>,
+    target: Option<GeometricPoint>,
     cert: f64,
     angle: TanableAngle,
     hat: Hat,
@@ -61,6 +64,7 @@ impl NaiveDetector {
     pub fn new(hat: Hat) -> NaiveDetector {
         NaiveDetector {
             point: None,
+            target: None,
             cert: 0.0,
             angle: TanableAngle::Angle(0.0),
             hat_side_points: (GeometricPoint::new(0, 0), GeometricPoint::new(0, 0)),
@@ -107,7 +111,10 @@ impl Detector for NaiveDetector {
     /// angle and certainty.
     fn detect_new_position(&mut self, img: &Mat, _old_pos: Option<Point>, p_c: &PointConverter) {
         let contours = get_contours(img, &self.hat.color_low, &self.hat.color_high);
-        let contour_option = get_best_fit_contour(&contours, self.hat.size_avg);
+        let contour_option = match &self.target {
+            Some(target) => get_closest_fitting_contour(&contours, self.hat.size_avg, &p_c.convert_to_image_coords(target)),
+            None => get_best_fit_contour(&contours, self.hat.size_avg),
+        };
 
         match contour_option {
             Some((contour, cert)) => {
@@ -120,15 +127,28 @@ impl Detector for NaiveDetector {
 
                 self.cert = cert;
                 self.angle = self.get_angle(&center, &contour_cent);
+                if self.target.is_some() {
+                    self.target = Some(center.clone());
+                }
                 self.point = Some(center);
             }
             None => {
                 self.cert = 0.0;
                 self.point = None;
+                self.target = None;
             }
         }
     }
 
+    fn change_hat(&mut self, hat: Hat) {
+        self.hat = hat;
+        self.target = None;
+    }
+
+    fn switch_target(&mut self, target: GeometricPoint) {
+        self.target = Some(target);
+    }
+
     /// Call this only if you want to visualize the detected points, and the angle.
     fn draw_on_image(&self, m_d: &mut MarkerDrawer) {
         let k = 100;
@@ -182,6 +202,27 @@ fn get_best_fit_contour(contours: &opencv::types::VectorOfVectorOfPoint, size_av
     })
 }
 
+/// Same as get_best_fit_contour, but out of the contours with a fitting area it chooses the one
+/// closest to the given point (in image coordinates).
+fn get_closest_fitting_contour(contours: &opencv::types::VectorOfVectorOfPoint, size_avg: f64, target: &Point) -> Option<(Vec<Point>, f64)> {
+    let distance = |contour: &Vec<Point>| {
+        let (sx, sy) = contour.iter().fold((0.0, 0.0), |(sx, sy), p| (sx + p.x as f64, sy + p.y as f64));
+        let n = contour.len().max(1) as f64;
+        (sx / n - target.x as f64).powi(2) + (sy / n - target.y as f64).powi(2)
+    };
+
+    contours.iter()
+        .map(|contour| (contour_area(&contour, false).unwrap(), contour))
+        .filter(|(area, _)| *area >= size_avg / 2.0 && *area <= size_avg * 1.5)
+        .map(|(area, contour)| {
+            let l = contour.iter()
+                .map(|p| Point::new(p.x, p.y))
+                .collect::<Vec<Point>>();
+            (l, (500.0 / (size_avg - area).abs()).min(1.0).max(0.0))
+        })
+        .min_by(|(a, _), (b, _)| distance(a).partial_cmp(&distance(b)).unwrap())
+}
+
 /// The area can only differ with a maximum of 50%
 fn get_contour_with_closest_area_to(c_with_area: &Vec<(f64, VectorOfPoint)>, size_avg: f64) -> (f64, f64, Option<&VectorOfPoint>) {
     c_with_area.iter()
@@ -0,0 +1,222 @@
+use opencv::core::*;
+use opencv::types::VectorOfPoint2f;
+
+use crate::traits::{Detector};
+
+use crate::models::geometric_point::GeometricPoint;
+use crate::models::hat::Hat;
+
+use crate::utils::point_converter::PointConverter;
+use crate::utils::opencv_custom::{to_grayscale, get_red};
+use crate::utils::optical_flow::{circular_mask, find_features, track_features, estimate_rigid_motion};
+use crate::utils::marker_drawer::MarkerDrawer;
+
+const PI: f64 = std::f64::consts::PI;
+
+/// The minimum certainty of a colour detection, that is trusted enough to start tracking from.
+const MIN_DETECTION_CERTAINTY: f64 = 0.5;
+/// The ratio of tracked features (compared to the ones found at the last colour detection) under
+/// which the flow is no longer trusted, and colour detection is run again.
+const MIN_FLOW_CONFIDENCE: f64 = 0.5;
+/// Under this amount of features no rotation can be reliably calculated.
+const MIN_FEATURES: usize = 4;
+/// Maximum number of features that are tracked on the hat.
+const MAX_FEATURES: i32 = 50;
+
+/// This detector wraps another (colour based) detector. After the wrapped detector finds the hat
+/// with a high enough certainty, it searches for corner features on the hat and follows them with
+/// pyramidal Lucas-Kanade optical flow in the next frames. The position and the angle of the hat
+/// are calculated from the movement of these features.
+///
+/// The wrapped detector is only run again every `redetect_interval` frames, or sooner if too many
+/// features were lost (the confidence of the flow dropped).
+///
+/// The angle is kept between -pi/2 and pi/2 in the same way the NaiveDetector does it.
+pub struct OpticalFlowDetector<D: Detector> {
+    detector: D,
+    feature_radius: i32,
+    redetect_interval: usize,
+    frames_since_detection: usize,
+    prev_gray: Option<Mat>,
+    features: VectorOfPoint2f,
+    features_at_detection: usize,
+    /// The center of the hat in image coordinates, kept as floats so that rounding errors don't add up.
+    center: (f64, f64),
+    detection_cert: f64,
+    point: Option<GeometricPoint>,
+    angle: Option<f64>,
+    cert: f64,
+}
+
+impl<D: Detector> OpticalFlowDetector<D> {
+    /// Requires the detector that will be used to find the hat by its colour, the average size of
+    /// the hat (same as in the Hat struct, it is used to decide where to look for features) and the
+    /// maximum number of frames after which the colour detection is run again.
+    ///
+    /// Usage:
+    ///
+    ///```
+    /// use rust_drone_follow::detectors::naive_detector::NaiveDetector;
+    /// use rust_drone_follow::detectors::optical_flow_detector::OpticalFlowDetector;
+    /// use rust_drone_follow::models::lab_color::LabColor;
+    /// use rust_drone_follow::models::hat::Hat;
+    /// // ...
+    /// # fn main() {
+    ///     let hat = Hat::new(
+    ///            LabColor::new(0, 20, -127),
+    ///            LabColor::new(80, 127, -20),
+    ///            1200.0
+    ///        );
+    ///     let detector = OpticalFlowDetector::new(NaiveDetector::new(hat), 1200.0, 15);
+    /// # }
+    /// ```
+    pub fn new(detector: D, hat_size: f64, redetect_interval: usize) -> OpticalFlowDetector<D> {
+        OpticalFlowDetector {
+            detector,
+            feature_radius: ((hat_size / PI).sqrt() * 1.2) as i32,
+            redetect_interval,
+            frames_since_detection: 0,
+            prev_gray: None,
+            features: VectorOfPoint2f::new(),
+            features_at_detection: 0,
+            center: (0.0, 0.0),
+            detection_cert: 0.0,
+            point: None,
+            angle: None,
+            cert: 0.0,
+        }
+    }
+
+    fn is_tracking(&self) -> bool {
+        self.features_at_detection > 0
+    }
+
+    fn stop_tracking(&mut self) {
+        self.features = VectorOfPoint2f::new();
+        self.features_at_detection = 0;
+    }
+
+    /// Runs the wrapped detector, and if the detection is certain enough starts tracking features
+    /// around the detected point.
+    fn detect_by_color(&mut self, img: &Mat, gray: &Mat, old_pos: Option<Point>, p_c: &PointConverter) {
+        self.detector.detect_new_position(img, old_pos, p_c);
+        self.point = self.detector.get_detected_position();
+        self.angle = self.detector.get_detected_angle();
+        self.cert = self.detector.get_detection_certainty();
+        self.detection_cert = self.cert;
+        self.frames_since_detection = 0;
+        self.stop_tracking();
+
+        if let Some(p) = &self.point {
+            if self.cert >= MIN_DETECTION_CERTAINTY {
+                let center = p_c.convert_to_image_coords(p);
+                self.features = find_features(gray, &circular_mask(gray, center, self.feature_radius), MAX_FEATURES);
+                if self.features.len() >= MIN_FEATURES {
+                    self.features_at_detection = self.features.len();
+                    self.center = (center.x as f64, center.y as f64);
+                }
+            }
+        }
+    }
+
+    /// Moves the hat with the tracked features. Returns false if the flow can no longer be trusted.
+    fn track(&mut self, prev_gray: &Mat, gray: &Mat, p_c: &PointConverter) -> bool {
+        let (old, new) = track_features(prev_gray, gray, &self.features);
+        let confidence = new.len() as f64 / self.features_at_detection as f64;
+        if new.len() < MIN_FEATURES || confidence < MIN_FLOW_CONFIDENCE {
+            return false;
+        }
+
+        let from = old.iter().map(|p| (p.x as f64, p.y as f64)).collect::<Vec<(f64, f64)>>();
+        let to = new.iter().map(|p| (p.x as f64, p.y as f64)).collect::<Vec<(f64, f64)>>();
+        let motion = match estimate_rigid_motion(&from, &to) {
+            Some(motion) => motion,
+            None => return false,
+        };
+
+        self.center = motion.apply(self.center.0, self.center.1);
+        self.point = Some(p_c.convert_from_image_coords(
+            &Point::new(self.center.0.round() as i32, self.center.1.round() as i32)
+        ));
+        // The y axis of the image points downwards, so a clockwise rotation on the image is a
+        // counter-clockwise one in the descartes coordinate system.
+        self.angle = self.angle.map(|angle| normalize_angle(angle - motion.angle));
+        self.cert = self.detection_cert * confidence;
+        self.features = new;
+        true
+    }
+}
+
+impl<D: Detector> Detector for OpticalFlowDetector<D> {
+    fn get_detected_position(&self) -> Option<GeometricPoint> {
+        self.point.clone()
+    }
+
+    fn get_detected_angle(&self) -> Option<f64> {
+        self.angle
+    }
+
+    fn get_detection_certainty(&self) -> f64 {
+        self.cert
+    }
+
+    /// Follows the features from the previous frame, and falls back to the wrapped detector if it
+    /// is time for a new colour detection or the features were lost.
+    fn detect_new_position(&mut self, img: &Mat, old_pos: Option<Point>, p_c: &PointConverter) {
+        let gray = to_grayscale(img);
+
+        let tracked = match self.prev_gray.take() {
+            Some(prev_gray) if self.is_tracking() && self.frames_since_detection < self.redetect_interval => {
+                self.frames_since_detection += 1;
+                self.track(&prev_gray, &gray, p_c)
+            }
+            _ => false
+        };
+        if !tracked {
+            self.detect_by_color(img, &gray, old_pos, p_c);
+        }
+
+        self.prev_gray = Some(gray);
+    }
+
+    /// Draws the markers of the wrapped detector after a colour detection, and the tracked features
+    /// while following them.
+    fn draw_on_image(&self, m_d: &mut MarkerDrawer) {
+        if !self.is_tracking() || self.frames_since_detection == 0 {
+            self.detector.draw_on_image(m_d);
+            return;
+        }
+
+        if let Some(p) = &self.point {
+            for feature in self.features.iter() {
+                let dx = (feature.x as f64 - self.center.0).round() as i32;
+                let dy = (feature.y as f64 - self.center.1).round() as i32;
+                m_d.point(&GeometricPoint::new(p.x + dx, p.y - dy), get_red());
+            }
+        }
+    }
+
+    /// Passes the hat to the wrapped detector, and runs the colour detection on the next frame.
+    fn change_hat(&mut self, hat: Hat) {
+        self.feature_radius = ((hat.size_avg / PI).sqrt() * 1.2) as i32;
+        self.detector.change_hat(hat);
+        self.stop_tracking();
+    }
+
+    /// Passes the target to the wrapped detector, and runs the colour detection on the next frame.
+    fn switch_target(&mut self, target: GeometricPoint) {
+        self.detector.switch_target(target);
+        self.stop_tracking();
+    }
+}
+
+/// Brings the angle between -pi/2 and pi/2, since the hat has no front and back in this regard.
+fn normalize_angle(angle: f64) -> f64 {
+    let mut angle = angle % PI;
+    if angle > PI / 2.0 {
+        angle -= PI;
+    } else if angle <= -PI / 2.0 {
+        angle += PI;
+    }
+    angle
+}
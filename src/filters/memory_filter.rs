@@ -1,6 +1,7 @@
 use crate::traits::{Filter};
 
 use crate::models::geometric_point::GeometricPoint;
+use crate::models::rigid_motion::RigidMotion;
 
 use crate::utils::marker_drawer::MarkerDrawer;
 use crate::utils::opencv_custom::get_blue;
@@ -14,6 +15,7 @@ pub struct MemoryFilter {
     vx: f64,
     vy: f64,
     cert: f64,
+    camera_motion: Option<RigidMotion>,
 }
 
 impl MemoryFilter {
@@ -28,20 +30,29 @@ impl MemoryFilter {
            cert: 0.0,
            vx: 0.0,
            vy: 0.0,
+           camera_motion: None,
        }
     }
 }
 
 impl Filter for MemoryFilter {
     /// Simply copies the estimation, that it got from the detector. vx and vy are calculated as
-    /// a difference of old point and the new point. If there is no new detection, retains the old
-    /// one, until given amount of frames.
-    fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, cert: f64) {
+    /// a difference of old point and the new point, divided by the time between them. If there is
+    /// no new detection, retains the old one, until given amount of frames.
+    fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, cert: f64, dt: f64) {
+        let camera_motion = self.camera_motion.take();
         match &self.point {
             Some(p) => {
                 if let Some(point) = &point {
-                    self.vx = (point.x - p.x) as f64;
-                    self.vy = (point.y - p.y) as f64;
+                    if dt > 0.0 {
+                        // Where the old point would be now if it only moved with the image.
+                        let (moved_x, moved_y) = match &camera_motion {
+                            Some(motion) => motion.apply(p.x as f64, p.y as f64),
+                            None => (p.x as f64, p.y as f64),
+                        };
+                        self.vx = (point.x as f64 - moved_x) / dt;
+                        self.vy = (point.y as f64 - moved_y) / dt;
+                    }
                 }
             }
             _ => { }
@@ -52,6 +63,11 @@ impl Filter for MemoryFilter {
                 self.point = Some(p);
             }
             None => {
+                // The retained point moves together with the image.
+                if let (Some(p), Some(motion)) = (&self.point, &camera_motion) {
+                    let (x, y) = motion.apply(p.x as f64, p.y as f64);
+                    self.point = Some(GeometricPoint::new(x.round() as i32, y.round() as i32));
+                }
                 if self.frames_unknown == self.max_frames_unknown {
                     self.point = None;
                     self.frames_unknown = 0;
@@ -65,6 +81,12 @@ impl Filter for MemoryFilter {
         self.cert = cert;
     }
 
+    /// The camera motion is subtracted from the next calculated vx and vy, so they will be
+    /// relative to the ground instead of the drone. A retained point is moved together with the image.
+    fn update_camera_motion(&mut self, motion: &RigidMotion) {
+        self.camera_motion = Some(motion.clone());
+    }
+
     fn get_estimated_position(&self) -> Option<GeometricPoint> {
         self.point.as_ref().map(|p| p.clone())
     }
@@ -90,4 +112,48 @@ impl Filter for MemoryFilter {
             m_d.point(p, get_blue());
         }
     }
+
+    fn reset(&mut self) {
+        *self = MemoryFilter::new(self.max_frames_unknown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::traits::Filter;
+
+    use crate::models::geometric_point::GeometricPoint;
+
+    use super::MemoryFilter;
+
+    #[test]
+    fn velocity_should_use_real_dt() {
+        let mut sut = MemoryFilter::new(3);
+        sut.update_estimation(Some(GeometricPoint::new(0, 0)), None, 1.0, 0.0);
+        sut.update_estimation(Some(GeometricPoint::new(10, -5)), None, 1.0, 0.25);
+
+        assert!(sut.get_estimated_vx() == 40.0 && sut.get_estimated_vy() == -20.0)
+    }
+
+    #[test]
+    fn zero_dt_should_keep_the_velocity() {
+        let mut sut = MemoryFilter::new(3);
+        sut.update_estimation(Some(GeometricPoint::new(0, 0)), None, 1.0, 0.0);
+        sut.update_estimation(Some(GeometricPoint::new(10, 0)), None, 1.0, 0.5);
+        sut.update_estimation(Some(GeometricPoint::new(30, 0)), None, 1.0, 0.0);
+
+        assert_eq!(sut.get_estimated_vx(), 20.0)
+    }
+
+    #[test]
+    fn retained_point_should_be_forgotten_after_max_frames_unknown() {
+        let mut sut = MemoryFilter::new(2);
+        sut.update_estimation(Some(GeometricPoint::new(4, 2)), None, 1.0, 0.0);
+        sut.update_estimation(None, None, 0.0, 0.1);
+        sut.update_estimation(None, None, 0.0, 0.1);
+        assert!(sut.get_estimated_position().is_some());
+
+        sut.update_estimation(None, None, 0.0, 0.1);
+        assert!(sut.get_estimated_position().is_none())
+    }
 }
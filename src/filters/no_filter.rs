@@ -1,6 +1,7 @@
 use crate::traits::{Filter};
 
 use crate::models::geometric_point::GeometricPoint;
+use crate::models::rigid_motion::RigidMotion;
 
 use crate::utils::marker_drawer::MarkerDrawer;
 use crate::utils::opencv_custom::get_blue;
@@ -13,6 +14,7 @@ pub struct NoFilter {
     vx: f64,
     vy: f64,
     cert: f64,
+    camera_motion: Option<RigidMotion>,
 }
 
 impl NoFilter {
@@ -24,23 +26,32 @@ impl NoFilter {
            cert: 0.0,
            vx: 0.0,
            vy: 0.0,
+           camera_motion: None,
        }
     }
 }
 
 impl Filter for NoFilter {
     /// Simply copies the estimation, that it got from the detector. vx and vy are calculated as
-    /// a difference of old point and the new point.
-    fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, cert: f64) {
+    /// a difference of old point and the new point, divided by the time between them.
+    fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, cert: f64, dt: f64) {
         match &self.point {
             Some(p) => {
                 if let Some(point) = &point {
-                    self.vx = (point.x - p.x) as f64;
-                    self.vy = (point.y - p.y) as f64;
+                    if dt > 0.0 {
+                        // Where the old point would be now if it only moved with the image.
+                        let (moved_x, moved_y) = match &self.camera_motion {
+                            Some(motion) => motion.apply(p.x as f64, p.y as f64),
+                            None => (p.x as f64, p.y as f64),
+                        };
+                        self.vx = (point.x as f64 - moved_x) / dt;
+                        self.vy = (point.y as f64 - moved_y) / dt;
+                    }
                 }
             }
             _ => { }
         }
+        self.camera_motion = None;
         self.point = point;
         if let Some(angle) = angle {
             self.angle = angle;
@@ -48,6 +59,12 @@ impl Filter for NoFilter {
         self.cert = cert;
     }
 
+    /// The camera motion is subtracted from the next calculated vx and vy, so they will be
+    /// relative to the ground instead of the drone.
+    fn update_camera_motion(&mut self, motion: &RigidMotion) {
+        self.camera_motion = Some(motion.clone());
+    }
+
     fn get_estimated_position(&self) -> Option<GeometricPoint> {
         self.point.as_ref().map(|p| p.clone())
     }
@@ -73,4 +90,46 @@ impl Filter for NoFilter {
             m_d.point(p, get_blue());
         }
     }
+
+    fn reset(&mut self) {
+        *self = NoFilter::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::traits::Filter;
+
+    use crate::models::geometric_point::GeometricPoint;
+
+    use super::NoFilter;
+
+    #[test]
+    fn velocity_should_use_real_dt() {
+        let mut sut = NoFilter::new();
+        sut.update_estimation(Some(GeometricPoint::new(0, 0)), None, 1.0, 0.0);
+        sut.update_estimation(Some(GeometricPoint::new(10, -5)), None, 1.0, 0.5);
+
+        assert!(sut.get_estimated_vx() == 20.0 && sut.get_estimated_vy() == -10.0)
+    }
+
+    #[test]
+    fn zero_dt_should_keep_the_velocity() {
+        let mut sut = NoFilter::new();
+        sut.update_estimation(Some(GeometricPoint::new(0, 0)), None, 1.0, 0.0);
+        sut.update_estimation(Some(GeometricPoint::new(0, 10)), None, 1.0, 0.5);
+        sut.update_estimation(Some(GeometricPoint::new(0, 30)), None, 1.0, 0.0);
+
+        assert_eq!(sut.get_estimated_vy(), 20.0)
+    }
+
+    #[test]
+    fn predicted_position_should_move_with_velocity() {
+        let mut sut = NoFilter::new();
+        sut.update_estimation(Some(GeometricPoint::new(0, 0)), None, 1.0, 0.0);
+        sut.update_estimation(Some(GeometricPoint::new(10, -5)), None, 1.0, 0.5);
+        let point = sut.predict_position(0.25).unwrap();
+
+        assert!(point.x == 15 && point.y == -8)
+    }
 }
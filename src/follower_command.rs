@@ -0,0 +1,26 @@
+use crate::HatFollowerSettings;
+
+use crate::models::geometric_point::GeometricPoint;
+use crate::models::hat::Hat;
+
+/// Commands that can be sent to a running HatFollower through the channel given to it in the
+/// constructor. They are handled between two frames.
+pub enum FollowerCommand {
+    /// Stops following, lands the drone and returns from run().
+    Stop,
+    /// Lands the drone, but keeps processing the video. Resume makes it take off again.
+    Land,
+    /// Stops the drone (it hovers in place), but keeps processing the video.
+    Pause,
+    /// Continues following after a Pause or a Land.
+    Resume,
+    /// Makes the detector look for a different hat.
+    ChangeHat(Hat),
+    /// Replaces the settings of the HatFollower.
+    UpdateSettings(Box<HatFollowerSettings>),
+    /// Makes the detector follow the hat closest to the given point (in the descartes coordinate
+    /// system of the image) if it sees more than one.
+    SwitchTarget(GeometricPoint),
+    /// Stops the drone immediately, lands it and returns from run().
+    EmergencyStop,
+}
@@ -0,0 +1,54 @@
+use crate::models::geometric_point::GeometricPoint;
+
+/// The state of a HatFollower.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FollowerState {
+    /// The drone follows the hat.
+    Following,
+    /// The drone hovers in place, the video is still processed.
+    Paused,
+    /// The drone is landed, the video is still processed.
+    Landed,
+    /// run() has returned.
+    Stopped,
+}
+
+/// Events published by a running HatFollower to every subscriber (see HatFollower::subscribe).
+/// Positions are in the descartes coordinate system of the image.
+#[derive(Clone)]
+pub enum FollowerEvent {
+    /// The result of the detection on a frame.
+    Detection {
+        frame_num: usize,
+        position: Option<GeometricPoint>,
+        angle: Option<f64>,
+        certainty: f64,
+    },
+    /// The estimation of the filter after a frame. vx and vy are in pixels/second.
+    Estimate {
+        frame_num: usize,
+        position: Option<GeometricPoint>,
+        angle: f64,
+        vx: f64,
+        vy: f64,
+        certainty: f64,
+    },
+    /// A move command that was sent to the drone.
+    CommandSent {
+        frame_num: usize,
+        left_right: f64,
+        back_front: f64,
+        down_up: f64,
+        turn_left_right: f64,
+    },
+    /// The HatFollower changed its state.
+    StateChanged(FollowerState),
+    /// An error that happened while running (for example the video couldn't be read).
+    Error(String),
+    /// An output (for example "video" or "commands") dropped items because its queue was full.
+    /// Count is the number dropped since the output was started.
+    Dropped {
+        output: String,
+        count: usize,
+    },
+}
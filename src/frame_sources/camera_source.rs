@@ -0,0 +1,219 @@
+use std::time::Instant;
+
+use opencv::core::{Mat, MatTrait, StsObjectNotFound};
+use opencv::videoio::{VideoCapture, VideoCaptureTrait, CAP_ANY, CAP_V4L2, CAP_PROP_FPS, CAP_PROP_FRAME_HEIGHT, CAP_PROP_FRAME_WIDTH};
+
+use crate::traits::FrameSource;
+
+use crate::models::frame::Frame;
+
+/// A camera given by its index, or by the path of its device (for example "/dev/video2").
+#[derive(Clone, PartialEq, Debug)]
+pub enum CameraDevice {
+    Index(i32),
+    Path(String),
+}
+
+impl CameraDevice {
+    /// Numbers are read as an index, anything else as the path of the device.
+    pub fn parse(device: &str) -> CameraDevice {
+        match device.trim().parse::<i32>() {
+            Ok(index) => CameraDevice::Index(index),
+            Err(_) => CameraDevice::Path(String::from(device.trim())),
+        }
+    }
+}
+
+/// The resolution and frame rate requested from the camera. The ones that are None are left at
+/// the default of the camera.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CameraOptions {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub fps: Option<f64>,
+}
+
+impl CameraOptions {
+    /// Keeps the defaults of the camera.
+    pub fn new() -> CameraOptions {
+        CameraOptions {
+            width: None,
+            height: None,
+            fps: None,
+        }
+    }
+}
+
+/// Provides the frames of a live camera (a webcam or any other device supported by OpenCV).
+///
+/// The camera may not support the requested resolution and frame rate, so the ones it actually
+/// uses are read back from it (or from the first frame if the camera doesn't report them), and
+/// reported to the HatFollower. The timestamps of the frames are the time they were read at,
+/// measured from the opening of the camera.
+pub struct CameraSource {
+    video: VideoCapture,
+    requested: CameraOptions,
+    height: usize,
+    width: usize,
+    fps: Option<f64>,
+    started_at: Instant,
+    /// The frame read while opening the camera to find out its resolution, with its timestamp.
+    first_frame: Option<(Mat, f64)>,
+}
+
+fn open_capture(device: &CameraDevice) -> opencv::Result<VideoCapture> {
+    match device {
+        CameraDevice::Index(index) => VideoCapture::new(*index, CAP_ANY),
+        CameraDevice::Path(path) => VideoCapture::from_file(path, CAP_V4L2)
+            .or_else(|_| VideoCapture::from_file(path, CAP_ANY)),
+    }
+}
+
+/// Returns the value of the property if the camera reports it.
+fn get_property(video: &VideoCapture, property: i32) -> Option<f64> {
+    video.get(property).ok().filter(|value| *value > 0.0)
+}
+
+impl CameraSource {
+    /// Opens the camera with its default resolution and frame rate, and panics if it can't be
+    /// opened (see open). The device is an index or the path of the device.
+    ///
+    /// Usage:
+    /// ```no_run
+    /// use rust_drone_follow::frame_sources::camera_source::CameraSource;
+    /// // ...
+    /// # fn main() {
+    ///     let source = CameraSource::new("/dev/video0");
+    /// # }
+    /// ```
+    pub fn new(device: &str) -> CameraSource {
+        CameraSource::with_options(device, CameraOptions::new())
+    }
+
+    /// Opens the camera, and requests the resolution and frame rate in the options from it. Panics
+    /// if it can't be opened (see open_with_options).
+    pub fn with_options(device: &str, options: CameraOptions) -> CameraSource {
+        CameraSource::open_with_options(device, options).unwrap_or_else(|e| panic!("{}", e.message))
+    }
+
+    /// Opens the camera with its default resolution and frame rate. Returns an error if it can't
+    /// be opened, or its resolution can't be found out.
+    pub fn open(device: &str) -> opencv::Result<CameraSource> {
+        CameraSource::open_with_options(device, CameraOptions::new())
+    }
+
+    /// Opens the camera, and requests the resolution and frame rate in the options from it.
+    /// Returns an error if it can't be opened, or its resolution can't be found out.
+    pub fn open_with_options(device: &str, options: CameraOptions) -> opencv::Result<CameraSource> {
+        let device = CameraDevice::parse(device);
+        let mut video = open_capture(&device)?;
+        if !video.is_opened().unwrap_or(false) {
+            return Err(opencv::Error::new(StsObjectNotFound, format!("Couldn't open the camera {:?}", device)));
+        }
+        if let Some(width) = options.width {
+            video.set(CAP_PROP_FRAME_WIDTH, width as f64)?;
+        }
+        if let Some(height) = options.height {
+            video.set(CAP_PROP_FRAME_HEIGHT, height as f64)?;
+        }
+        if let Some(fps) = options.fps {
+            video.set(CAP_PROP_FPS, fps)?;
+        }
+        let started_at = Instant::now();
+
+        let mut first_frame = None;
+        let reported_size = get_property(&video, CAP_PROP_FRAME_WIDTH)
+            .zip(get_property(&video, CAP_PROP_FRAME_HEIGHT))
+            .map(|(width, height)| (width as usize, height as usize));
+        let (width, height) = match reported_size {
+            Some(size) => size,
+            None => {
+                // The camera doesn't report its resolution, so it is read from a frame instead.
+                let mut image = Mat::default()?;
+                if !video.read(&mut image).unwrap_or(false) {
+                    return Err(opencv::Error::new(StsObjectNotFound, format!("Couldn't read a frame from the camera {:?}", device)));
+                }
+                let size = (image.cols() as usize, image.rows() as usize);
+                first_frame = Some((image, started_at.elapsed().as_secs_f64()));
+                size
+            }
+        };
+        let fps = get_property(&video, CAP_PROP_FPS);
+
+        Ok(CameraSource {
+            video,
+            requested: options,
+            height,
+            width,
+            fps,
+            started_at,
+            first_frame,
+        })
+    }
+
+    /// Returns the frame rate the camera reports, if it reports it.
+    pub fn get_fps(&self) -> Option<f64> {
+        self.fps
+    }
+
+    /// Returns the resolution and frame rate requested from the camera, the ones it uses may be
+    /// different (see get_video_width, get_video_height and get_fps).
+    pub fn get_requested_options(&self) -> CameraOptions {
+        self.requested
+    }
+}
+
+/// Returns the error for a camera that doesn't use the requested resolution, if it doesn't.
+fn resolution_mismatch(requested: &CameraOptions, width: usize, height: usize) -> Option<String> {
+    if requested.width.unwrap_or(width) == width && requested.height.unwrap_or(height) == height {
+        return None;
+    }
+    Some(format!("The camera uses {}x{} instead of the requested resolution", width, height))
+}
+
+impl FrameSource for CameraSource {
+    fn get_video_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_video_width(&self) -> usize {
+        self.width
+    }
+
+    /// Reads the next frame of the camera.
+    fn get_next_frame(&mut self, frame: &mut Frame) -> opencv::Result<bool> {
+        if let Some((image, timestamp)) = self.first_frame.take() {
+            frame.image = image;
+            frame.timestamp = timestamp;
+            return Ok(true);
+        }
+        let success = self.video.read(&mut frame.image)?;
+        frame.timestamp = self.started_at.elapsed().as_secs_f64();
+        Ok(success)
+    }
+
+    /// Reports a camera that doesn't use the requested resolution.
+    fn get_warnings(&self) -> Vec<String> {
+        resolution_mismatch(&self.requested, self.width, self.height).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolution_mismatch, CameraDevice, CameraOptions};
+
+    #[test]
+    fn numbers_should_be_read_as_indexes() {
+        assert_eq!(CameraDevice::parse("1"), CameraDevice::Index(1));
+        assert_eq!(CameraDevice::parse("/dev/video2"), CameraDevice::Path(String::from("/dev/video2")))
+    }
+
+    #[test]
+    fn different_resolution_should_be_reported() {
+        let mut requested = CameraOptions::new();
+        requested.width = Some(1280);
+
+        assert_eq!(resolution_mismatch(&requested, 1280, 720), None);
+        assert_eq!(resolution_mismatch(&requested, 640, 480).unwrap(), "The camera uses 640x480 instead of the requested resolution")
+    }
+}
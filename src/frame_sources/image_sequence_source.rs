@@ -0,0 +1,329 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use opencv::core::{Mat, MatTrait, StsObjectNotFound, StsOutOfRange, StsUnmatchedSizes};
+use opencv::imgcodecs::{imread, IMREAD_COLOR};
+
+use crate::traits::FrameSource;
+
+use crate::models::frame::Frame;
+
+/// The extensions of the files read from a directory.
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "tif", "tiff"];
+
+/// Provides the images of a directory (or the ones matching a pattern like "frames/img_*.png") as
+/// frames.
+///
+/// The images are read in the natural order of their names (img_2.png comes before img_10.png).
+/// The resolution is read from the first image, and every image must have the same size. The
+/// timestamps of the frames are read from a sidecar file (see load_timestamps), or calculated
+/// from the fps.
+pub struct ImageSequenceSource {
+    files: Vec<PathBuf>,
+    timestamps: Option<Vec<f64>>,
+    height: usize,
+    width: usize,
+    /// Frame rate used for the timestamps if they aren't loaded from a file.
+    pub fps: f64,
+    /// If it is true, the sequence starts over after the last image, otherwise it stops.
+    pub looping: bool,
+    next: usize,
+    /// The time added to the timestamps, so they keep increasing when the sequence loops.
+    loop_offset: f64,
+}
+
+/// Compares two file names so that the numbers in them are compared by their value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let (x_trimmed, y_trimmed) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_trimmed.len().cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Matches a file name against a pattern, where * matches any number of characters, and ? matches
+/// one character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    // matches[j] is true if the processed part of the pattern matches the first j characters.
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+    for p in pattern {
+        let previous = matches.clone();
+        matches[0] = p == '*' && previous[0];
+        for j in 1..=name.len() {
+            matches[j] = match p {
+                '*' => previous[j] || matches[j - 1],
+                '?' => previous[j - 1],
+                c => previous[j - 1] && c == name[j - 1],
+            };
+        }
+    }
+    matches[name.len()]
+}
+
+/// Returns the images of a directory, or the files matching the pattern in the last part of the
+/// path, in natural order.
+fn list_images(source: &str) -> std::io::Result<Vec<PathBuf>> {
+    let path = Path::new(source);
+    let (directory, pattern) = if path.is_dir() {
+        (path, None)
+    } else {
+        let pattern = path.file_name().and_then(|p| p.to_str())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("Invalid image pattern: {}", source)))?;
+        (path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or_else(|| Path::new(".")), Some(pattern))
+    };
+
+    let mut files = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|file| file.is_file())
+        .filter(|file| {
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            match pattern {
+                Some(pattern) => wildcard_match(pattern, name),
+                None => file.extension().and_then(|e| e.to_str())
+                    .map(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+                    .unwrap_or(false),
+            }
+        })
+        .collect::<Vec<PathBuf>>();
+    files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(files)
+}
+
+/// Reads the timestamps of the images from the text of a sidecar file. Every row is either
+/// "timestamp" (the rows are in the order of the images) or "file_name timestamp".
+fn parse_timestamps(text: &str, files: &[PathBuf]) -> std::io::Result<Vec<f64>> {
+    let invalid = |message: String| Error::new(ErrorKind::InvalidData, message);
+    let mut by_index = Vec::new();
+    let mut by_name = HashMap::new();
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let values = line.split_whitespace().collect::<Vec<&str>>();
+        let timestamp = values.last().unwrap().parse::<f64>()
+            .map_err(|_| invalid(format!("Invalid timestamp in row {}: {}", i + 1, line)))?;
+        match values.len() {
+            1 => by_index.push(timestamp),
+            2 => {
+                by_name.insert(String::from(values[0]), timestamp);
+            }
+            _ => return Err(invalid(format!("Invalid row {}: {}", i + 1, line))),
+        }
+    }
+
+    if by_name.is_empty() {
+        if by_index.len() < files.len() {
+            return Err(invalid(format!("There are {} timestamps for {} images", by_index.len(), files.len())));
+        }
+        by_index.truncate(files.len());
+        return Ok(by_index);
+    }
+    files.iter()
+        .map(|file| {
+            let name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            by_name.get(name).copied()
+                .ok_or_else(|| invalid(format!("There is no timestamp for {}", name)))
+        })
+        .collect()
+}
+
+/// Reads an image, and returns an error if it can't be read.
+fn read_image(file: &Path) -> opencv::Result<Mat> {
+    let image = imread(&file.to_string_lossy(), IMREAD_COLOR)?;
+    if image.empty()? {
+        return Err(opencv::Error::new(StsObjectNotFound, format!("Couldn't read the image {}", file.display())));
+    }
+    Ok(image)
+}
+
+impl ImageSequenceSource {
+    /// Reads the list of images from a directory, or a pattern of file names, and panics if they
+    /// can't be read (see open).
+    ///
+    /// Usage:
+    /// ```no_run
+    /// use rust_drone_follow::frame_sources::image_sequence_source::ImageSequenceSource;
+    /// // ...
+    /// # fn main() {
+    ///     let source = ImageSequenceSource::new("dataset/frames/*.png");
+    /// # }
+    /// ```
+    pub fn new(source: &str) -> ImageSequenceSource {
+        ImageSequenceSource::open(source).unwrap_or_else(|e| panic!("{}", e.message))
+    }
+
+    /// Reads the list of images from a directory, or a pattern of file names, and the resolution
+    /// from the first image. Returns an error if there are no images, or the first one can't be
+    /// read.
+    pub fn open(source: &str) -> opencv::Result<ImageSequenceSource> {
+        let files = list_images(source)
+            .map_err(|e| opencv::Error::new(StsObjectNotFound, format!("Couldn't list the images of {}: {}", source, e)))?;
+        let first = files.first()
+            .ok_or_else(|| opencv::Error::new(StsObjectNotFound, format!("There are no images in {}", source)))?;
+        let image = read_image(first)?;
+        Ok(ImageSequenceSource {
+            width: image.cols() as usize,
+            height: image.rows() as usize,
+            files,
+            timestamps: None,
+            fps: 30.0,
+            looping: false,
+            next: 0,
+            loop_offset: 0.0,
+        })
+    }
+
+    /// Reads the timestamps (in seconds) of the images from a sidecar file. Every row of it is
+    /// either "timestamp" (the rows are in the order of the images) or "file_name timestamp".
+    pub fn load_timestamps(&mut self, filename: &str) -> std::io::Result<()> {
+        let text = fs::read_to_string(filename)?;
+        self.timestamps = Some(parse_timestamps(&text, &self.files)?);
+        Ok(())
+    }
+
+    /// Returns the images of the sequence in the order they are given out.
+    pub fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn get_timestamp(&self, index: usize) -> f64 {
+        match &self.timestamps {
+            Some(timestamps) => timestamps[index] - timestamps[0],
+            None => index as f64 / self.fps,
+        }
+    }
+}
+
+impl FrameSource for ImageSequenceSource {
+    fn get_video_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_video_width(&self) -> usize {
+        self.width
+    }
+
+    /// Reads the next image of the sequence. Returns an error if its size differs from the first one.
+    fn get_next_frame(&mut self, frame: &mut Frame) -> opencv::Result<bool> {
+        if self.next >= self.files.len() {
+            if !self.looping || self.files.is_empty() {
+                return Ok(false);
+            }
+            // The next round starts one frame interval after the last image.
+            let interval = if self.files.len() > 1 {
+                self.get_timestamp(self.files.len() - 1) / (self.files.len() - 1) as f64
+            } else {
+                1.0 / self.fps
+            };
+            self.loop_offset += self.get_timestamp(self.files.len() - 1) + interval;
+            self.next = 0;
+        }
+
+        let file = &self.files[self.next];
+        frame.image = read_image(file)?;
+        let (width, height) = (frame.image.cols() as usize, frame.image.rows() as usize);
+        if (width, height) != (self.width, self.height) {
+            return Err(opencv::Error::new(StsUnmatchedSizes, format!("The image {} is {}x{} instead of {}x{}",
+                                                                     file.display(), width, height, self.width, self.height)));
+        }
+        frame.timestamp = self.loop_offset + self.get_timestamp(self.next);
+        self.next += 1;
+        Ok(true)
+    }
+
+    fn get_position(&self) -> Option<usize> {
+        Some(self.next)
+    }
+
+    fn seek(&mut self, frame_num: usize) -> opencv::Result<bool> {
+        if frame_num >= self.files.len() {
+            return Err(opencv::Error::new(StsOutOfRange, format!("The sequence has no image {}", frame_num)));
+        }
+        self.next = frame_num;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{natural_cmp, wildcard_match, list_images, parse_timestamps};
+
+    #[test]
+    fn numbers_should_be_compared_by_value() {
+        assert_eq!(natural_cmp("img_2.png", "img_10.png"), Ordering::Less);
+        assert_eq!(natural_cmp("img_010.png", "img_9.png"), Ordering::Greater);
+        assert_eq!(natural_cmp("a_1.png", "b_0.png"), Ordering::Less)
+    }
+
+    #[test]
+    fn wildcards_should_match() {
+        assert!(wildcard_match("img_*.png", "img_0012.png"));
+        assert!(wildcard_match("img_??.png", "img_12.png"));
+        assert!(!wildcard_match("img_*.png", "img_12.jpg"));
+        assert!(!wildcard_match("img_?.png", "img_12.png"))
+    }
+
+    #[test]
+    fn directory_should_be_listed_in_natural_order() {
+        let directory = std::env::temp_dir().join(format!("image_sequence_source_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for name in &["frame_10.png", "frame_2.png", "frame_1.jpg", "notes.txt"] {
+            fs::write(directory.join(name), "").unwrap();
+        }
+
+        let all = list_images(directory.to_str().unwrap()).unwrap();
+        let png = list_images(directory.join("frame_*.png").to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let names = |files: Vec<PathBuf>| files.iter()
+            .map(|f| f.file_name().unwrap().to_str().unwrap().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(names(all), vec!["frame_1.jpg", "frame_2.png", "frame_10.png"]);
+        assert_eq!(names(png), vec!["frame_2.png", "frame_10.png"])
+    }
+
+    #[test]
+    fn timestamps_should_be_read_by_name_or_by_row() {
+        let files = vec![PathBuf::from("d/a.png"), PathBuf::from("d/b.png")];
+
+        assert_eq!(parse_timestamps("b.png 0.5\na.png 0.1\n", &files).unwrap(), vec![0.1, 0.5]);
+        assert_eq!(parse_timestamps("0.1\n0.2\n0.3\n", &files).unwrap(), vec![0.1, 0.2]);
+        assert!(parse_timestamps("0.1\n", &files).is_err())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod camera_source;
+pub mod image_sequence_source;
+pub mod video_file_source;
+
+pub use camera_source::CameraSource;
+pub use image_sequence_source::ImageSequenceSource;
+pub use video_file_source::VideoFileSource;
@@ -0,0 +1,158 @@
+use opencv::core::{StsObjectNotFound, StsOutOfRange};
+use opencv::videoio::{VideoCapture, VideoCaptureTrait, CAP_ANY, CAP_PROP_FPS, CAP_PROP_FRAME_COUNT, CAP_PROP_FRAME_HEIGHT, CAP_PROP_FRAME_WIDTH, CAP_PROP_POS_FRAMES};
+
+use crate::traits::FrameSource;
+
+use crate::models::frame::Frame;
+
+/// Provides the frames of a video file, along with it's resolution.
+///
+/// The resolution and the frame rate are read from the file, and the timestamps of the frames are
+/// calculated from the frame rate. The video can be started from a given frame or time (see
+/// seek_to_frame and seek_to_time), and stopped before the end (see end_frame).
+///
+/// You can use it together with the MockController to test the tracking system on a prerecorded
+/// video, or with a real controller to fly the drone on a recorded video.
+pub struct VideoFileSource {
+    video: VideoCapture,
+    height: usize,
+    width: usize,
+    fps: f64,
+    frame_count: Option<usize>,
+    frame_num: usize,
+    /// If it is set, the video stops before this frame.
+    pub end_frame: Option<usize>,
+}
+
+/// Returns the value of the property if the file has it.
+fn get_property(video: &VideoCapture, property: i32) -> Option<f64> {
+    video.get(property).ok().filter(|value| *value > 0.0)
+}
+
+/// Returns the number of the frame shown at the given time of the video.
+fn frame_at_time(seconds: f64, fps: f64) -> usize {
+    (seconds.max(0.0) * fps).round() as usize
+}
+
+impl VideoFileSource {
+    /// Opens the video file, and panics if it can't be read (see open).
+    ///
+    /// Usage:
+    /// ```no_run
+    /// use rust_drone_follow::frame_sources::video_file_source::VideoFileSource;
+    /// // ...
+    /// # fn main() {
+    ///     let source = VideoFileSource::new("video_file.mp4");
+    /// # }
+    /// ```
+    pub fn new(filename: &str) -> VideoFileSource {
+        VideoFileSource::open(filename).unwrap_or_else(|e| panic!("{}", e.message))
+    }
+
+    /// Opens the video file, and reads its resolution and frame rate. Returns an error if the
+    /// file can't be opened, or its resolution can't be read.
+    pub fn open(filename: &str) -> opencv::Result<VideoFileSource> {
+        let video = VideoCapture::from_file(filename, CAP_ANY)?;
+        if !video.is_opened()? {
+            return Err(opencv::Error::new(StsObjectNotFound, format!("Couldn't open the video file {}", filename)));
+        }
+        let (width, height) = match (get_property(&video, CAP_PROP_FRAME_WIDTH), get_property(&video, CAP_PROP_FRAME_HEIGHT)) {
+            (Some(width), Some(height)) => (width as usize, height as usize),
+            _ => return Err(opencv::Error::new(StsObjectNotFound, format!("Couldn't read the resolution of the video file {}", filename))),
+        };
+        // Some containers don't store the frame rate, in that case 30 fps is assumed.
+        let fps = get_property(&video, CAP_PROP_FPS).unwrap_or(30.0);
+        let frame_count = get_property(&video, CAP_PROP_FRAME_COUNT).map(|count| count as usize);
+        Ok(VideoFileSource {
+            video,
+            height,
+            width,
+            fps,
+            frame_count,
+            frame_num: 0,
+            end_frame: None,
+        })
+    }
+
+    /// Returns the frame rate of the video.
+    pub fn get_fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// Returns the number of frames in the video, if the file stores it.
+    pub fn get_frame_count(&self) -> Option<usize> {
+        self.frame_count
+    }
+
+    /// Returns the number of the frame shown at the given time (in seconds) of the video.
+    pub fn get_frame_at_time(&self, seconds: f64) -> usize {
+        frame_at_time(seconds, self.fps)
+    }
+
+    /// Continues the video from the given frame. The timestamps of the frames stay the time they
+    /// are shown at in the video.
+    pub fn seek_to_frame(&mut self, frame_num: usize) -> opencv::Result<()> {
+        if matches!(self.frame_count, Some(count) if frame_num >= count) {
+            return Err(opencv::Error::new(StsOutOfRange, format!("The video has no frame {}", frame_num)));
+        }
+        if !self.video.set(CAP_PROP_POS_FRAMES, frame_num as f64)? {
+            return Err(opencv::Error::new(StsOutOfRange, format!("Couldn't seek to frame {}", frame_num)));
+        }
+        self.frame_num = frame_num;
+        Ok(())
+    }
+
+    /// Continues the video from the given time (in seconds).
+    pub fn seek_to_time(&mut self, seconds: f64) -> opencv::Result<()> {
+        self.seek_to_frame(self.get_frame_at_time(seconds))
+    }
+
+    /// Plays only the frames shown between the given times (in seconds) of the video.
+    pub fn play_range(&mut self, start: f64, end: f64) -> opencv::Result<()> {
+        self.seek_to_time(start)?;
+        self.end_frame = Some(self.get_frame_at_time(end));
+        Ok(())
+    }
+}
+
+impl FrameSource for VideoFileSource {
+    fn get_video_height(&self) -> usize {
+        self.height
+    }
+
+    fn get_video_width(&self) -> usize {
+        self.width
+    }
+
+    /// Reads the next frame of the video.
+    fn get_next_frame(&mut self, frame: &mut Frame) -> opencv::Result<bool> {
+        if matches!(self.end_frame, Some(end) if self.frame_num >= end) {
+            return Ok(false);
+        }
+        let success = self.video.read(&mut frame.image)?;
+        frame.timestamp = self.frame_num as f64 / self.fps;
+        self.frame_num += 1;
+        Ok(success)
+    }
+
+    fn get_position(&self) -> Option<usize> {
+        Some(self.frame_num)
+    }
+
+    fn seek(&mut self, frame_num: usize) -> opencv::Result<bool> {
+        self.seek_to_frame(frame_num)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::frame_at_time;
+
+    #[test]
+    fn time_should_be_rounded_to_the_nearest_frame() {
+        assert_eq!(frame_at_time(2.0, 30.0), 60);
+        assert_eq!(frame_at_time(0.49, 10.0), 5);
+        assert_eq!(frame_at_time(-1.0, 30.0), 0)
+    }
+}
@@ -1,18 +1,64 @@
+use crate::models::camera_model::CameraModel;
+
+use crate::utils::bounded_queue::QueueOptions;
+use crate::utils::state_exporter::StateExportFormat;
+use crate::utils::telemetry_publisher::TelemetryFormat;
+use crate::utils::video_exporter::VideoExportOptions;
+
 /// Settings for HatFollower
+#[derive(Debug)]
 pub struct HatFollowerSettings {
     /// Radius of circle around the center that is considered to be okay (if the drone is over this
     /// circle it tries to stay there, otherwise it tries to get over it).
     pub center_threshold: f64,
     /// Minimum amount of change in speeds needed to issue a new move command.
     pub min_change: f64,
-    /// Under how many frames(as time measurement) we want the drone to reach the center.
+    /// Under how many frames we want the drone to reach the center in the pixel control mode. It
+    /// stays a frame count (kv of the controllers is tuned for it), so it has to be retuned for
+    /// another frame rate, the metric control mode uses time_to_be_centered (in seconds) instead.
     pub frames_to_be_centered: f64,
     /// Sets whether the program should save the video.
     pub save_to_file: Option<String>,
-    /// Sets whether the program should save commands in a file denoting the frame
+    /// Sets whether the program should save the video without the markers drawn on it (it can be
+    /// used together with save_to_file).
+    pub save_raw_to_file: Option<String>,
+    /// The codec, frame rate, container and resolution of the saved videos. Changing it while
+    /// running finishes the videos, and starts new ones numbered after the change (video_1.mp4).
+    pub video_export_options: VideoExportOptions,
+    /// Sets whether the program should save commands in a file denoting the frame (in rows of
+    /// frame_num left_right back_front down_up turn_left_right timestamp, see read_command_log).
     pub save_commands: Option<String>,
     /// Sets whether the program should show the image real-time.
     pub show_video: bool,
+    /// Sets whether the video can be paused, stepped, sped up and jumped in from the keyboard of
+    /// the highgui window, with the number of the frame and the detection and estimation written
+    /// on it (see PlaybackControls). It only works in run, together with show_video.
+    pub playback_controls: bool,
+    /// If it is set, the image is streamed as MJPEG over HTTP on this port (viewable from a browser).
+    pub stream_video: Option<u16>,
+    /// The address the MJPEG server listens on. It is "127.0.0.1" by default, so only this
+    /// computer can watch the stream, "0.0.0.0" makes it available on every network interface.
+    pub stream_video_address: String,
+    /// If it is set, a TelemetryRecord is sent over UDP to this address (for example
+    /// "192.168.1.10:9000") after every frame. If the address can't be used, an Error event is
+    /// published and it is set back to None.
+    pub stream_telemetry: Option<String>,
+    /// The format of the records sent to stream_telemetry.
+    pub telemetry_format: TelemetryFormat,
+    /// If it is set, the raw frames, the telemetry and the output of every step are saved into this
+    /// directory, so the flight can be replayed later with the ReplayController. If the directory
+    /// can't be created or written, an Error event is published and it is set back to None.
+    pub record_session: Option<String>,
+    /// If it is set, the full state of every frame (detection, estimation, the calculated command
+    /// and whether it was sent) is saved into this file for offline analysis. If the file can't be
+    /// created or written, an Error event is published and it is set back to None.
+    pub export_state: Option<String>,
+    /// The format of the file of export_state.
+    pub state_export_format: StateExportFormat,
+    /// The size of the queues of the files written besides the videos (save_commands,
+    /// record_session and export_state), and what happens when they are full. The number of the
+    /// dropped items is published in Dropped events.
+    pub export_queue_options: QueueOptions,
     /// Sets whether the program should draw the detection markers on the video.
     pub draw_detection: bool,
     /// Sets whether the program should draw the filter markers on the video.
@@ -23,6 +69,27 @@ pub struct HatFollowerSettings {
     pub counteract_velocity: bool,
     /// Turn only when above the target
     pub turn_range: f64,
+    /// Estimates the motion of the drone from the movement of the whole image, and lets the filter
+    /// subtract it from the velocity of the hat.
+    pub compensate_ego_motion: bool,
+    /// Focal length of the camera in pixels. If it is set, detections are re-projected to a level,
+    /// downwards facing camera using the attitude reported by the controller, before they reach the
+    /// filter.
+    pub attitude_compensation: Option<f64>,
+    /// Calculates the target velocities in m/s and rad/s (instead of pixels/frame), and lets the
+    /// controller convert them to its commands. It requires the camera_model to be set, without it
+    /// an Error event is published and it is set back to false.
+    pub metric_control: bool,
+    /// The camera model used by the metric control mode.
+    pub camera_model: Option<CameraModel>,
+    /// Altitude of the drone in metres, used by the metric control mode if the controller doesn't
+    /// report it.
+    pub altitude: f64,
+    /// Under how many seconds we want the drone to reach the center in the metric control mode.
+    pub time_to_be_centered: f64,
+    /// Delay in seconds between capturing a frame on the drone and a command taking effect. If it
+    /// is set, the control acts on the position of the hat predicted by the filter for this time.
+    pub latency: f64,
 
 }
 
@@ -34,13 +101,31 @@ impl HatFollowerSettings {
             min_change: 0.3,
             frames_to_be_centered: 10.0,
             save_to_file: None,
+            save_raw_to_file: None,
+            video_export_options: VideoExportOptions::new(),
             save_commands: None,
             show_video: true,
+            playback_controls: false,
+            stream_video: None,
+            stream_video_address: String::from("127.0.0.1"),
+            stream_telemetry: None,
+            telemetry_format: TelemetryFormat::Json,
+            record_session: None,
+            export_state: None,
+            state_export_format: StateExportFormat::Csv,
+            export_queue_options: QueueOptions::new(),
             draw_detection: false,
             draw_filter: false,
             draw_center: false,
             counteract_velocity: false,
             turn_range: 15.0,
+            compensate_ego_motion: false,
+            attitude_compensation: None,
+            metric_control: false,
+            camera_model: None,
+            altitude: 2.0,
+            time_to_be_centered: 1.0,
+            latency: 0.0,
         }
     }
 
@@ -51,13 +136,31 @@ impl HatFollowerSettings {
             min_change: 0.3,
             frames_to_be_centered: 10.0,
             save_to_file: Some(String::from("debug_video.mp4")),
+            save_raw_to_file: None,
+            video_export_options: VideoExportOptions::new(),
             save_commands: Some(String::from("debug_commands.txt")),
             show_video: true,
+            playback_controls: false,
+            stream_video: None,
+            stream_video_address: String::from("127.0.0.1"),
+            stream_telemetry: None,
+            telemetry_format: TelemetryFormat::Json,
+            record_session: None,
+            export_state: None,
+            state_export_format: StateExportFormat::Csv,
+            export_queue_options: QueueOptions::new(),
             draw_detection: true,
             draw_filter: true,
             draw_center: true,
             counteract_velocity: false,
             turn_range: 15.0,
+            compensate_ego_motion: false,
+            attitude_compensation: None,
+            metric_control: false,
+            camera_model: None,
+            altitude: 2.0,
+            time_to_be_centered: 1.0,
+            latency: 0.0,
         }
     }
 
@@ -68,13 +171,31 @@ impl HatFollowerSettings {
             min_change: 0.3,
             frames_to_be_centered: 10.0,
             save_to_file: None,
+            save_raw_to_file: None,
+            video_export_options: VideoExportOptions::new(),
             save_commands: None,
             show_video: false,
+            playback_controls: false,
+            stream_video: None,
+            stream_video_address: String::from("127.0.0.1"),
+            stream_telemetry: None,
+            telemetry_format: TelemetryFormat::Json,
+            record_session: None,
+            export_state: None,
+            state_export_format: StateExportFormat::Csv,
+            export_queue_options: QueueOptions::new(),
             draw_detection: false,
             draw_filter: false,
             draw_center: false,
             counteract_velocity: false,
             turn_range: 15.0,
+            compensate_ego_motion: false,
+            attitude_compensation: None,
+            metric_control: false,
+            camera_model: None,
+            altitude: 2.0,
+            time_to_be_centered: 1.0,
+            latency: 0.0,
         }
     }
 }
\ No newline at end of file
@@ -1,42 +1,93 @@
 pub mod detectors;
 pub mod filters;
 pub mod controllers;
+pub mod frame_sources;
 pub mod models;
 pub mod utils;
 
+pub mod follower_command;
+pub mod follower_event;
+mod pipeline;
+pub mod step_output;
 pub mod hat_follower_settings;
 pub mod traits;
 
-use std::sync::mpsc::Receiver;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
 use std::f64::consts::PI;
+use std::time::Instant;
 
-use opencv::core::*;
+use opencv::core::{Mat, MatTrait};
 use opencv::highgui::*;
 
 use crate::traits::*;
+pub use follower_command::FollowerCommand;
+pub use follower_event::{FollowerEvent, FollowerState};
 pub use hat_follower_settings::HatFollowerSettings;
+pub use step_output::StepOutput;
 
-use crate::utils::video_exporter::VideoExporter;
+use crate::models::camera_model::CameraModel;
+use crate::models::frame::Frame;
+use crate::models::geometric_point::GeometricPoint;
+use crate::models::rigid_motion::RigidMotion;
+use crate::models::state_record::StateRecord;
+use crate::models::telemetry_record::TelemetryRecord;
+
+use crate::utils::video_exporter::{VideoExporter, VideoExportOptions, numbered_filename};
+use crate::utils::mjpeg_server::MjpegServer;
+use crate::utils::playback_controls::{PlaybackControls, PlaybackAction, draw_overlay};
+use crate::utils::telemetry_publisher::TelemetryPublisher;
+use crate::utils::session_recorder::SessionRecorder;
+use crate::utils::state_exporter::StateExporter;
 use crate::utils::point_converter::PointConverter;
 use crate::utils::opencv_custom::get_red;
 use crate::utils::text_exporter::TextExporter;
 use crate::utils::marker_drawer::MarkerDrawer;
+use crate::utils::frame_registration::FrameRegistration;
+use crate::utils::attitude_compensator::AttitudeCompensator;
+
 
+/// The result of running the detector on a frame.
+struct Detection {
+    position: Option<GeometricPoint>,
+    angle: Option<f64>,
+    certainty: f64,
+    camera_motion: Option<RigidMotion>,
+}
 
-/// The heart of the following mechanism. This struct orchestrates the three parts, in order to
+/// The heart of the following mechanism. This struct orchestrates the four parts, in order to
 /// make the drone follow the object. It's only function is run() which initializes the drone, and
 /// starts following the person wearing the hat.
-pub struct HatFollower<D: Detector, C: Controller, F: Filter> {
-    detector: D,
-    controller: C,
+pub struct HatFollower<D: Detector, S: FrameSource, C: Controller, F: Filter> {
+    // The detector, the source and the controller are shared with the other threads in pipelined
+    // mode.
+    detector: Arc<Mutex<D>>,
+    source: Arc<Mutex<S>>,
+    controller: Arc<Mutex<C>>,
     filter: F,
     p_c: PointConverter,
+    frame_registration: FrameRegistration,
     last_params: (f64, f64, f64, f64),
-    stop_channel: Option<Receiver<i32>>,
+    // The command calculated for the last frame, even if it wasn't sent.
+    computed_command: Option<(f64, f64, f64, f64)>,
+    last_timestamp: Option<f64>,
+    frame_num: usize,
+    dt: f64,
+    command_channel: Option<Receiver<FollowerCommand>>,
+    subscribers: Vec<Sender<FollowerEvent>>,
+    telemetry_publisher: Option<TelemetryPublisher>,
+    session_recorder: Option<SessionRecorder>,
+    state_exporter: Option<StateExporter>,
+    // The number of dropped items last published for each output.
+    reported_dropped: HashMap<String, usize>,
+    paused: bool,
+    landed: bool,
     settings: HatFollowerSettings,
 }
 
-impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
+impl<D: Detector, S: FrameSource, C: Controller, F: Filter> HatFollower<D, S, C, F> {
 
     /// Returns a new HatFollower. Can be initialized with any fitting parameter, depending on your
     /// needs.
@@ -45,10 +96,11 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
     /// thread it will run indefinitely !!!
     ///
     /// Usage example:
-    /// ```
+    /// ```no_run
     /// use rust_drone_follow::detectors::naive_detector::NaiveDetector;
     /// use rust_drone_follow::filters::no_filter::NoFilter;
     /// use rust_drone_follow::controllers::mock_controller::MockController;
+    /// use rust_drone_follow::frame_sources::video_file_source::VideoFileSource;
     ///
     /// use rust_drone_follow::models::hat::Hat;
     /// use rust_drone_follow::models::lab_color::LabColor;
@@ -63,27 +115,271 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
     ///            LabColor::new(80, 127, -20),
     ///            1200.0
     ///        )),
-    ///        MockController::new("test.mp4", 1280, 720),
+    ///        VideoFileSource::new("test.mp4"),
+    ///        MockController::new(),
     ///        NoFilter::new(),
     ///        HatFollowerSettings::new(),
     ///        None,
     ///    );
     /// }
     /// ```
-    pub fn new(detector: D, controller: C, filter: F, settings: HatFollowerSettings, stop_channel: Option<Receiver<i32>>) -> HatFollower<D, C, F> {
+    pub fn new(detector: D, source: S, controller: C, filter: F, settings: HatFollowerSettings, command_channel: Option<Receiver<FollowerCommand>>) -> HatFollower<D, S, C, F> {
         HatFollower {
-            p_c: PointConverter::new(controller.get_video_width(), controller.get_video_height()),
-            detector,
-            controller,
+            p_c: PointConverter::new(source.get_video_width(), source.get_video_height()),
+            detector: Arc::new(Mutex::new(detector)),
+            source: Arc::new(Mutex::new(source)),
+            controller: Arc::new(Mutex::new(controller)),
             filter,
+            frame_registration: FrameRegistration::new(),
             last_params: (0.0, 0.0, 0.0, 0.0),
-            stop_channel,
+            computed_command: None,
+            last_timestamp: None,
+            frame_num: 0,
+            dt: 0.0,
+            command_channel,
+            subscribers: Vec::new(),
+            telemetry_publisher: None,
+            session_recorder: None,
+            state_exporter: None,
+            reported_dropped: HashMap::new(),
+            paused: false,
+            landed: false,
             settings
         }
     }
 
-    // Calculates the necessary speed that is needed for the hat to be in the center of the frame.
-    // It is in dx/dt where dx is the coordinate difference and dt is the time between frames.
+    fn controller(&self) -> MutexGuard<'_, C> {
+        self.controller.lock().unwrap()
+    }
+
+    fn detector(&self) -> MutexGuard<'_, D> {
+        self.detector.lock().unwrap()
+    }
+
+    fn source(&self) -> MutexGuard<'_, S> {
+        self.source.lock().unwrap()
+    }
+
+    /// Returns a Receiver on which every event of the HatFollower will be published (detections,
+    /// estimations, commands sent to the drone, state changes and errors). Can be called any number
+    /// of times, every subscriber gets every event.
+    pub fn subscribe(&mut self) -> Receiver<FollowerEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Sends the event to every subscriber, and forgets the ones that dropped their Receiver.
+    fn publish(&mut self, event: FollowerEvent) {
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Publishes the number of items the output dropped, if it grew since it was last published.
+    fn publish_dropped(&mut self, output: &str, count: usize) {
+        let reported = self.reported_dropped.entry(String::from(output)).or_insert(0);
+        if count > *reported {
+            *reported = count;
+            self.publish(FollowerEvent::Dropped { output: String::from(output), count });
+        }
+    }
+
+    /// Publishes the errors and the dropped rows of the command log.
+    fn report_text_exporter(&mut self, text_exporter: &TextExporter) {
+        for e in text_exporter.get_errors() {
+            self.publish(FollowerEvent::Error(e));
+        }
+        self.publish_dropped("commands", text_exporter.get_dropped());
+    }
+
+    /// Finishes the command log, the recorded session and the state export, and publishes their
+    /// errors.
+    fn finish_exports(&mut self, mut text_exporter: TextExporter) {
+        let mut errors = text_exporter.finish();
+        if let Some(mut recorder) = self.session_recorder.take() {
+            errors.extend(recorder.finish());
+        }
+        if let Some(mut exporter) = self.state_exporter.take() {
+            errors.extend(exporter.finish());
+        }
+        for e in errors {
+            self.publish(FollowerEvent::Error(e));
+        }
+    }
+
+    /// Publishes the problems of the frame source (see FrameSource::get_warnings) as errors.
+    fn report_source_warnings(&mut self) {
+        let warnings = self.source().get_warnings();
+        for warning in warnings {
+            self.publish(FollowerEvent::Error(warning));
+        }
+    }
+
+    /// Turns off the settings that can't be used, and publishes an error for each of them.
+    fn validate_settings(&mut self) {
+        if self.settings.metric_control && self.settings.camera_model.is_none() {
+            self.settings.metric_control = false;
+            self.publish(FollowerEvent::Error(String::from(
+                "The metric control mode requires a camera model in the settings, the pixel control is used instead")));
+        }
+    }
+
+    fn get_state(&self) -> FollowerState {
+        if self.landed {
+            FollowerState::Landed
+        } else if self.paused {
+            FollowerState::Paused
+        } else {
+            FollowerState::Following
+        }
+    }
+
+    fn publish_state(&mut self) {
+        self.publish(FollowerEvent::StateChanged(self.get_state()));
+    }
+
+    /// Sends the record of the frame to the ground station, if it is set in the settings. If the
+    /// telemetry can't be sent to the address, an error is published and stream_telemetry is
+    /// turned off in the settings.
+    fn publish_telemetry(&mut self, timestamp: f64, output: &StepOutput) {
+        let current = self.telemetry_publisher.as_ref().map(|p| (p.get_address(), p.get_format()));
+        let wanted = self.settings.stream_telemetry.as_deref().map(|address| (address, self.settings.telemetry_format));
+        if current != wanted {
+            let started = wanted.map(|(address, format)| TelemetryPublisher::new(address, format)
+                .map_err(|e| format!("Couldn't start sending the telemetry to {}: {}", address, e)));
+            self.telemetry_publisher = None;
+            match started {
+                Some(Ok(publisher)) => self.telemetry_publisher = Some(publisher),
+                Some(Err(e)) => {
+                    self.settings.stream_telemetry = None;
+                    self.publish(FollowerEvent::Error(e));
+                }
+                None => {}
+            }
+        }
+
+        if let Some(publisher) = &self.telemetry_publisher {
+            let record = TelemetryRecord {
+                timestamp,
+                frame_num: output.frame_num as u64,
+                detection: output.detected_position.clone(),
+                detection_certainty: output.detection_certainty,
+                estimate: output.estimated_position.clone(),
+                estimated_angle: output.estimated_angle,
+                estimated_vx: output.estimated_vx,
+                estimated_vy: output.estimated_vy,
+                estimation_certainty: output.estimation_certainty,
+                command: self.last_params,
+                state: self.get_state(),
+                battery: self.controller().get_telemetry().battery,
+            };
+            // Lost telemetry shouldn't stop the drone.
+            let _ = publisher.publish(&record);
+        }
+    }
+
+    /// Saves the state of the frame into the file set in the settings (if any). If the file can't
+    /// be created or written, an error is published and export_state is turned off in the settings.
+    fn export_state(&mut self, timestamp: f64, output: &StepOutput) {
+        let current = self.state_exporter.as_ref().map(|e| (e.get_filename(), e.get_format()));
+        let wanted = self.settings.export_state.as_deref().map(|filename| (filename, self.settings.state_export_format));
+        if current != wanted {
+            let started = wanted.map(|(filename, format)| StateExporter::with_queue_options(filename, format, self.settings.export_queue_options)
+                .map_err(|e| format!("Couldn't create the state export file {}: {}", filename, e)));
+            if let Some(mut exporter) = self.state_exporter.take() {
+                for e in exporter.finish() {
+                    self.publish(FollowerEvent::Error(e));
+                }
+            }
+            match started {
+                Some(Ok(exporter)) => self.state_exporter = Some(exporter),
+                Some(Err(e)) => {
+                    self.settings.export_state = None;
+                    self.publish(FollowerEvent::Error(e));
+                }
+                None => {}
+            }
+        }
+
+        if let Some(exporter) = &self.state_exporter {
+            exporter.export(&StateRecord {
+                timestamp,
+                frame_num: output.frame_num,
+                dt: output.dt,
+                state: self.get_state(),
+                detected_position: output.detected_position.clone(),
+                detected_angle: output.detected_angle,
+                detection_certainty: output.detection_certainty,
+                estimated_position: output.estimated_position.clone(),
+                estimated_angle: output.estimated_angle,
+                estimated_vx: output.estimated_vx,
+                estimated_vy: output.estimated_vy,
+                estimation_certainty: output.estimation_certainty,
+                command: self.computed_command,
+                command_sent: output.command.is_some(),
+            });
+            let mut errors = exporter.get_errors();
+            let dropped = exporter.get_dropped();
+            self.publish_dropped("state", dropped);
+            if !errors.is_empty() {
+                if let Some(mut exporter) = self.state_exporter.take() {
+                    errors.extend(exporter.finish());
+                }
+                self.settings.export_state = None;
+                for e in errors {
+                    self.publish(FollowerEvent::Error(e));
+                }
+            }
+        }
+    }
+
+    /// Saves the raw frame and the output of the step into the session directory, if it is set in
+    /// the settings. If the session can't be created or written, an error is published and
+    /// record_session is turned off in the settings.
+    fn record_frame(&mut self, frame: &Frame, output: &StepOutput) {
+        let current = self.session_recorder.as_ref().map(|r| r.get_directory().to_path_buf());
+        let wanted = self.settings.record_session.as_ref().map(std::path::PathBuf::from);
+        if current != wanted {
+            if let Some(mut recorder) = self.session_recorder.take() {
+                for e in recorder.finish() {
+                    self.publish(FollowerEvent::Error(e));
+                }
+            }
+            let started = self.settings.record_session.as_deref().map(|directory| {
+                SessionRecorder::new(directory, self.p_c.get_width(), self.p_c.get_height(), &*self.controller(), &self.settings)
+                    .map_err(|e| format!("Couldn't create the session directory {}: {}", directory, e))
+            });
+            match started {
+                Some(Ok(recorder)) => self.session_recorder = Some(recorder),
+                Some(Err(e)) => {
+                    self.settings.record_session = None;
+                    self.publish(FollowerEvent::Error(e));
+                }
+                None => {}
+            }
+        }
+
+        if let Some(recorder) = &mut self.session_recorder {
+            let telemetry = self.controller.lock().unwrap().get_telemetry();
+            recorder.record(frame, &telemetry, output);
+            let mut errors = recorder.get_errors();
+            let dropped = recorder.get_dropped();
+            self.publish_dropped("session", dropped);
+            if !errors.is_empty() {
+                if let Some(mut recorder) = self.session_recorder.take() {
+                    errors.extend(recorder.finish());
+                }
+                self.settings.record_session = None;
+                for e in errors {
+                    self.publish(FollowerEvent::Error(e));
+                }
+            }
+        }
+    }
+
+    // Calculates the necessary speed that is needed for the hat to be in the center of the frame
+    // in frames_to_be_centered frames. It is in pixels/frame, which kv of the controllers is tuned
+    // for, so it depends on the offset of the hat only, and not on the time between the last two
+    // frames. The metric control mode is the one calculated in seconds (see calculate_metric_vs).
     fn calculate_speed_to_center(&self, dist: i32) -> f64 {
         if dist.abs() as f64 > self.settings.center_threshold {
             return dist as f64 / self.settings.frames_to_be_centered;
@@ -91,23 +387,34 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
         0.0
     }
 
+    // Returns the position the control acts on: the estimated position of the hat, predicted ahead
+    // by the latency between capturing a frame and the command taking effect.
+    fn get_target_position(&self) -> Option<GeometricPoint> {
+        if self.settings.latency > 0.0 {
+            return self.filter.predict_position(self.settings.latency);
+        }
+        self.filter.get_estimated_position()
+    }
+
     fn calculate_new_vs(&mut self) -> (f64, f64) {
         // If there is no detected point, the drone should stop.
-        if let None = self.filter.get_estimated_position() {
+        if let None = self.get_target_position() {
             return (0.0, 0.0);
         }
 
-        let est_position = self.filter.get_estimated_position().unwrap();
+        let est_position = self.get_target_position().unwrap();
         let mut vx_to_center = self.calculate_speed_to_center(est_position.x);
         let mut vy_to_center = self.calculate_speed_to_center(est_position.y);
 
         // Feature that needs testing.
+        // The filter gives the velocity in pixels/s, which is converted to pixels/frame using the
+        // real time between the last two frames.
         if self.settings.counteract_velocity {
-            vx_to_center -= self.filter.get_estimated_vx();
-            vy_to_center -= self.filter.get_estimated_vy();
+            vx_to_center -= self.filter.get_estimated_vx() * self.dt;
+            vy_to_center -= self.filter.get_estimated_vy() * self.dt;
         }
 
-        let kv = self.controller.get_kv();
+        let kv = self.controller().get_kv();
         (
             ((vx_to_center) * kv).min(1.0).max(-1.0),
             ((vy_to_center) * kv).min(1.0).max(-1.0)
@@ -115,7 +422,7 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
     }
 
     fn calculate_new_turn(&mut self) -> f64 {
-        let ka = self.controller.get_ka();
+        let ka = self.controller().get_ka();
         let ninety = ((PI / 2.0 - self.filter.get_estimated_angle()) * ka).min(1.0).max(-1.0);
         let minus_ninety = ((PI / - 2.0 - self.filter.get_estimated_angle()) * ka).min(1.0).max(-1.0);
 
@@ -128,12 +435,83 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
         -minus_ninety
     }
 
-    fn control_the_drone(&mut self, frame_num: usize, text_exporter: &mut TextExporter) {
+    // Calculates the velocity (in m/s) needed for the point directly under the drone to reach the
+    // hat in time_to_be_centered seconds.
+    fn calculate_metric_vs(&self, camera_model: &CameraModel, altitude: f64) -> (f64, f64) {
+        // If there is no detected point, the drone should stop.
+        let est_position = match self.get_target_position() {
+            Some(p) => p,
+            None => return (0.0, 0.0),
+        };
+
+        let ground = self.p_c.convert_to_ground(&est_position, camera_model, altitude);
+        let time = self.settings.time_to_be_centered;
+        let threshold = self.settings.center_threshold;
+        let mut vx_to_center = if est_position.x.abs() as f64 > threshold { ground.x / time } else { 0.0 };
+        let mut vy_to_center = if est_position.y.abs() as f64 > threshold { ground.y / time } else { 0.0 };
+
+        // Feature that needs testing.
+        if self.settings.counteract_velocity && self.dt > 0.0 {
+            // The filter gives the velocity in pixels/s, which is converted by projecting the
+            // hat's position after dt to the ground as well.
+            let image_point = self.p_c.convert_to_image_coords(&est_position);
+            let (x, y) = (image_point.x as f64, image_point.y as f64);
+            let moved = camera_model.image_to_ground(
+                x + self.filter.get_estimated_vx() * self.dt,
+                y - self.filter.get_estimated_vy() * self.dt,
+                altitude
+            );
+            let current = camera_model.image_to_ground(x, y, altitude);
+            vx_to_center -= (moved.x - current.x) / self.dt;
+            vy_to_center -= (moved.y - current.y) / self.dt;
+        }
+
+        (vx_to_center, vy_to_center)
+    }
+
+    // Calculates the turn rate (in rad/s) needed for the hat to be turned in the right direction in
+    // time_to_be_centered seconds.
+    fn calculate_metric_turn(&self) -> f64 {
+        let ninety = PI / 2.0 - self.filter.get_estimated_angle();
+        let minus_ninety = PI / - 2.0 - self.filter.get_estimated_angle();
+
+        // The same (-) as in calculate_new_turn.
+        if ninety.abs() <= minus_ninety.abs() {
+            return -ninety / self.settings.time_to_be_centered;
+        }
+        -minus_ninety / self.settings.time_to_be_centered
+    }
+
+    // Calculates the new command in physical units, and lets the controller convert it to its own
+    // command range.
+    fn calculate_metric_command(&self, camera_model: &CameraModel) -> (f64, f64, f64) {
+        let altitude = self.controller().get_telemetry().altitude.unwrap_or(self.settings.altitude);
+
+        let (vx, vy) = self.calculate_metric_vs(camera_model, altitude);
+        let turn = self.calculate_metric_turn();
+        let (new_vx, new_vy, _, new_turn) = self.controller().convert_metric(vx, vy, 0.0, turn);
+
+        (new_vx, new_vy, new_turn)
+    }
+
+    /// Decides the command that should be sent to the drone. Returns None if it is paused, landed,
+    /// or the command didn't change enough since the last one.
+    fn calculate_command(&mut self) -> Option<(f64, f64, f64, f64)> {
+        if self.paused || self.landed {
+            self.computed_command = None;
+            return None;
+        }
         let min_change = self.settings.min_change;
 
-        let (new_vx, new_vy) = self.calculate_new_vs();
+        let (new_vx, new_vy, turn) = match (self.settings.metric_control, &self.settings.camera_model) {
+            (true, Some(camera_model)) => self.calculate_metric_command(camera_model),
+            _ => {
+                let (new_vx, new_vy) = self.calculate_new_vs();
+                (new_vx, new_vy, self.calculate_new_turn())
+            }
+        };
         let new_turn= if new_vx.abs() + new_vy.abs() < self.settings.turn_range {
-                self.calculate_new_turn()
+                turn
             } else {
                 0.0
             };
@@ -141,60 +519,309 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
         // Check if a minimum change of speed is reached, in order not to have an overflow of move
         // commands if it's not necessary.
         let (old_vx, old_vy, old_vz, old_turn) = self.last_params;
+        self.computed_command = Some((new_vx, new_vy, old_vz, new_turn));
         if (new_vx - old_vx).abs() + (new_vy - old_vy).abs() + (new_turn - old_turn).abs() > min_change {
-            if let Some(filename) = &self.settings.save_commands {
-                text_exporter.save_row(filename.as_str(), format!("{} {} {} {} {}\n", frame_num, new_vx, new_vy, old_vz, new_turn));
-            }
-            self.controller.move_all(new_vx, new_vy, old_vz, new_turn);
             self.last_params = (new_vx, new_vy, old_vz, new_turn);
+            return Some(self.last_params);
         }
+        None
     }
 
-    fn main_loop(&mut self, img: &mut Mat, frame_num: usize, video_exporter: &mut VideoExporter, text_exporter: &mut TextExporter) {
-        let mut m_d = MarkerDrawer::new();
-        let point_for_detector = self.filter.get_estimated_position();
-        self.detector.detect_new_position(
-            &img,
-            point_for_detector.map(|gp| self.p_c.convert_to_image_coords( &gp)),
-            &self.p_c);
+    fn control_the_drone(&mut self, command: (f64, f64, f64, f64), text_exporter: &mut TextExporter) {
+        let (new_vx, new_vy, new_vz, new_turn) = command;
+        if let Some(filename) = &self.settings.save_commands {
+            text_exporter.save_row(filename.as_str(), format!("{} {} {} {} {} {}\n", self.frame_num, new_vx, new_vy, new_vz, new_turn, self.last_timestamp.unwrap_or(0.0)));
+        }
+        self.controller().move_all(new_vx, new_vy, new_vz, new_turn);
+        self.publish(FollowerEvent::CommandSent {
+            frame_num: self.frame_num,
+            left_right: new_vx,
+            back_front: new_vy,
+            down_up: new_vz,
+            turn_left_right: new_turn,
+        });
+    }
+
+    /// Runs the detector on the image, and estimates the motion of the camera if a FrameRegistration
+    /// is given.
+    fn detect(detector: &mut D, frame_registration: Option<&mut FrameRegistration>, img: &Mat, point_for_detector: Option<GeometricPoint>, p_c: &PointConverter) -> Detection {
+        detector.detect_new_position(
+            img,
+            point_for_detector.map(|gp| p_c.convert_to_image_coords( &gp)),
+            p_c);
+
+        Detection {
+            position: detector.get_detected_position(),
+            angle: detector.get_detected_angle(),
+            certainty: detector.get_detection_certainty(),
+            camera_motion: frame_registration.and_then(|f_r| f_r.register(img, p_c)),
+        }
+    }
+
+    /// Feeds the detection of a frame to the filter, and calculates the command for the drone.
+    ///
+    /// A replayed frame (given by its number, counted from 1) was already processed once: the
+    /// filter is updated for the overlay, but frame_num isn't increased, no command is calculated,
+    /// and no events or telemetry are published for it.
+    fn process_detection(&mut self, timestamp: f64, detection: Detection, replayed: Option<usize>) -> StepOutput {
+        if replayed.is_none() {
+            self.frame_num += 1;
+        }
+        let frame_num = replayed.unwrap_or(self.frame_num);
+        self.dt = self.last_timestamp.map(|t| timestamp - t).unwrap_or(0.0);
+        self.last_timestamp = Some(timestamp);
+
+        if let Some(motion) = &detection.camera_motion {
+            self.filter.update_camera_motion(motion);
+        }
+
+        if replayed.is_none() {
+            self.publish(FollowerEvent::Detection {
+                frame_num,
+                position: detection.position.clone(),
+                angle: detection.angle,
+                certainty: detection.certainty,
+            });
+        }
+
+        let mut detected_position = detection.position.clone();
+        if let Some(focal_length) = self.settings.attitude_compensation {
+            let telemetry = self.controller().get_telemetry();
+            detected_position = detected_position
+                .map(|p| AttitudeCompensator::new(focal_length).compensate(&p, &telemetry));
+        }
 
         self.filter.update_estimation(
-            self.detector.get_detected_position(),
-            self.detector.get_detected_angle(),
-            self.detector.get_detection_certainty()
+            detected_position,
+            detection.angle,
+            detection.certainty,
+            self.dt
         );
+        if replayed.is_none() {
+            self.publish(FollowerEvent::Estimate {
+                frame_num,
+                position: self.filter.get_estimated_position(),
+                angle: self.filter.get_estimated_angle(),
+                vx: self.filter.get_estimated_vx(),
+                vy: self.filter.get_estimated_vy(),
+                certainty: self.filter.get_estimation_certainty(),
+            });
+        }
 
-        // Drawing on the image
-        if self.settings.draw_detection {
-            self.detector.draw_on_image(&mut m_d);
+        let output = StepOutput {
+            frame_num,
+            dt: self.dt,
+            detected_position: detection.position,
+            detected_angle: detection.angle,
+            detection_certainty: detection.certainty,
+            estimated_position: self.filter.get_estimated_position(),
+            estimated_angle: self.filter.get_estimated_angle(),
+            estimated_vx: self.filter.get_estimated_vx(),
+            estimated_vy: self.filter.get_estimated_vy(),
+            estimation_certainty: self.filter.get_estimation_certainty(),
+            command: if replayed.is_none() { self.calculate_command() } else { None },
+        };
+        if replayed.is_none() {
+            self.publish_telemetry(timestamp, &output);
         }
+
+        output
+    }
+
+    /// Processes one frame: runs the detection and the filter on it, and calculates the command for
+    /// the drone, without sending it, drawing or saving anything. It can be used to embed the
+    /// follower in your own loop, or to process frames that don't come from the FrameSource (the
+    /// controller is still asked for telemetry and metric conversion if the settings need them).
+    ///
+    /// The timestamps of the frames are used to calculate the time between them, so they have to
+    /// be increasing. The returned command is only Some if it should be sent to the drone (it is
+    /// treated as sent when it is returned).
+    pub fn step(&mut self, frame: &Frame) -> StepOutput {
+        self.process_frame(frame, None)
+    }
+
+    /// Runs the detection on the frame and processes it like step. See process_detection for the
+    /// replayed frames.
+    fn process_frame(&mut self, frame: &Frame, replayed: Option<usize>) -> StepOutput {
+        let frame_registration = if self.settings.compensate_ego_motion {
+            Some(&mut self.frame_registration)
+        } else {
+            None
+        };
+        let detection = Self::detect(
+            &mut self.detector.lock().unwrap(),
+            frame_registration,
+            &frame.image,
+            self.filter.get_estimated_position(),
+            &self.p_c
+        );
+
+        self.process_detection(frame.timestamp, detection, replayed)
+    }
+
+    /// Adds the markers of the filter and the center to the ones of the detector, depending on the
+    /// settings.
+    fn draw_markers(&self, m_d: &mut MarkerDrawer) {
         if self.settings.draw_filter {
-            self.filter.draw_on_image(&mut m_d);
+            self.filter.draw_on_image(m_d);
         }
         if self.settings.draw_center {
             m_d.circle(&self.p_c.get_center(), self.settings.center_threshold as i32, get_red());
         }
+    }
+
+    /// Processes, draws, saves and shows a frame. If playback controls are given, their overlay is
+    /// written on the shown image. A replayed frame (the number of a frame visited again after
+    /// stepping back or jumping, counted from 1) is only processed and shown: no command, event
+    /// or telemetry is sent for it, and it isn't recorded, exported or saved again.
+    fn main_loop(&mut self, frame: &mut Frame, video_outputs: &mut VideoOutputs, text_exporter: &mut TextExporter, playback: Option<&PlaybackControls>, replayed: Option<usize>) -> StepOutput {
+        let output = self.process_frame(frame, replayed);
+        if replayed.is_none() {
+            self.record_frame(frame, &output);
+            self.export_state(frame.timestamp, &output);
+        }
 
-        // Applies the given markers to the image.
-        m_d.draw_on_image(img, &self.p_c);
+        let mut m_d = MarkerDrawer::new();
 
-        // Save to video file
-        if let Some(filename) = &self.settings.save_to_file {
-            video_exporter.save_frame(filename.as_str(), img);
+        // Drawing on the image
+        if self.settings.draw_detection {
+            self.detector().draw_on_image(&mut m_d);
         }
+        self.draw_markers(&mut m_d);
 
-        self.control_the_drone(frame_num, text_exporter);
+        if let Some(command) = output.command {
+            self.control_the_drone(command, text_exporter);
+        }
+        self.report_text_exporter(text_exporter);
 
-        // Show video file
-        if self.settings.show_video {
-            imshow("Image", img).unwrap();
-            opencv::highgui::wait_key(3).unwrap();
+        let overlay = playback.map(|controls| controls.get_overlay(self.source().get_position(), &output));
+        let mut settings = self.present_settings();
+        if replayed.is_some() {
+            settings.save_to_file = None;
+            settings.save_raw_to_file = None;
+        }
+        let events = present_frame(&mut frame.image, frame.timestamp, m_d, &self.p_c, &settings, overlay.as_deref(), video_outputs);
+        for event in events {
+            self.publish(event);
         }
+        output
     }
 
-    fn have_received_stop_command(&mut self) -> bool {
-        if let Some(receiver) = &mut self.stop_channel {
-            if let Ok(_) = receiver.try_recv() {
+    /// Waits until the next frame should be read according to the playback controls, and handles
+    /// the keys pressed in the meantime. The shown frame is redrawn when the overlay changes.
+    /// Returns true if the HatFollower should stop.
+    fn wait_for_playback(&mut self, controls: &mut PlaybackControls, img: &Mat, output: &StepOutput, read_at: Instant) -> bool {
+        loop {
+            let key = wait_key(controls.get_delay(output.dt, read_at.elapsed().as_secs_f64())).unwrap();
+            let position = self.source().get_position();
+            match controls.handle_key(key, position) {
+                PlaybackAction::Stop => return true,
+                PlaybackAction::Seek(frame_num) => {
+                    let result = self.source().seek(frame_num);
+                    match result {
+                        Ok(true) => {
+                            // The time between the frames and the motion of the image are
+                            // meaningless across a jump.
+                            self.last_timestamp = None;
+                            self.filter.reset();
+                            self.frame_registration = FrameRegistration::new();
+                            return false;
+                        }
+                        Ok(false) => self.publish(FollowerEvent::Error(String::from("The frame source can't seek"))),
+                        Err(e) => self.publish(FollowerEvent::Error(format!("Couldn't seek to frame {}: {}", frame_num, e))),
+                    }
+                }
+                PlaybackAction::Continue => {}
+            }
+            if controls.should_read_frame() {
+                return false;
+            }
+            if key >= 0 {
+                show_image(img, Some(&controls.get_overlay(position, output)));
+            }
+            if self.handle_commands() {
+                return true;
+            }
+        }
+    }
+
+    /// Returns the current settings of the video outputs.
+    fn present_settings(&self) -> PresentSettings {
+        PresentSettings {
+            save_to_file: self.settings.save_to_file.clone(),
+            save_raw_to_file: self.settings.save_raw_to_file.clone(),
+            video_export_options: self.settings.video_export_options.clone(),
+            show_video: self.settings.show_video,
+            stream_video: self.settings.stream_video,
+            stream_video_address: self.settings.stream_video_address.clone(),
+        }
+    }
+
+    /// Handles a command received through the command channel. Returns true if the HatFollower
+    /// should stop.
+    fn handle_command(&mut self, command: FollowerCommand) -> bool {
+        match command {
+            FollowerCommand::Stop => {
+                return true;
+            }
+            FollowerCommand::EmergencyStop => {
+                self.controller().stop();
+                self.controller().land();
+                self.landed = true;
+                return true;
+            }
+            FollowerCommand::Land => {
+                if !self.landed {
+                    self.controller().stop();
+                    self.controller().land();
+                    self.landed = true;
+                    self.last_params = (0.0, 0.0, 0.0, 0.0);
+                    self.publish_state();
+                }
+            }
+            FollowerCommand::Pause => {
+                if !self.paused && !self.landed {
+                    self.controller().stop();
+                    self.last_params = (0.0, 0.0, 0.0, 0.0);
+                }
+                if !self.paused {
+                    self.paused = true;
+                    self.publish_state();
+                }
+            }
+            FollowerCommand::Resume => {
+                if self.landed || self.paused {
+                    if self.landed {
+                        self.controller().takeoff();
+                    }
+                    self.landed = false;
+                    self.paused = false;
+                    self.publish_state();
+                }
+            }
+            FollowerCommand::ChangeHat(hat) => {
+                self.detector().change_hat(hat);
+            }
+            FollowerCommand::UpdateSettings(settings) => {
+                self.settings = *settings;
+                self.validate_settings();
+            }
+            FollowerCommand::SwitchTarget(point) => {
+                self.detector().switch_target(point);
+            }
+        }
+        false
+    }
+
+    /// Handles every command that arrived since the last frame. Returns true if the HatFollower
+    /// should stop.
+    fn handle_commands(&mut self) -> bool {
+        let commands = match &self.command_channel {
+            Some(receiver) => receiver.try_iter().collect::<Vec<FollowerCommand>>(),
+            None => Vec::new(),
+        };
+        for command in commands {
+            if self.handle_command(command) {
                 return true;
             }
         }
@@ -202,33 +829,408 @@ impl<D: Detector, C: Controller, F: Filter> HatFollower<D, C, F> {
     }
 
     /// Initializes the drone, and makes it follow the person wearing the hat. It can only be stopped
-    /// by sending a message through the channel whose receiver was given as a parameter in the constructor.
+    /// by sending a Stop or EmergencyStop command through the channel whose receiver was given as a
+    /// parameter in the constructor (or by the end of the video).
     pub fn run(&mut self) {
-        self.controller.init();
-        self.controller.takeoff();
+        self.controller().init();
+        self.controller().takeoff();
+        self.landed = false;
+        self.paused = false;
+        self.publish_state();
+        self.validate_settings();
+        self.report_source_warnings();
 
-        let mut video_exporter = VideoExporter::new();
-        let mut text_exporter = TextExporter::new();
-        let mut img = Mat::zeros_size(Size::new(1,1), CV_8U).unwrap().to_mat().unwrap();
-        let mut frame_num = 1;
+        let mut video_outputs = VideoOutputs::new();
+        let mut text_exporter = TextExporter::with_queue_options(self.settings.export_queue_options);
+        let mut frame = Frame::new();
+        let mut playback = if self.settings.playback_controls && self.settings.show_video {
+            Some(PlaybackControls::new())
+        } else {
+            None
+        };
+        // The frames before this position were already processed, they are only shown again when
+        // the playback steps back or jumps to them.
+        let mut new_frames_from = 0;
         loop {
-            if self.have_received_stop_command() {
+            if self.handle_commands() {
                 break;
             }
-            match self.controller.get_next_frame(&mut img) {
+            self.controller().tick();
+            let position = self.source().get_position();
+            let next_frame = self.source().get_next_frame(&mut frame);
+            match next_frame {
                 Ok(true) => {
-                    self.main_loop(&mut img, frame_num, &mut video_exporter, &mut text_exporter);
+                    let read_at = Instant::now();
+                    let replayed = position.filter(|p| *p < new_frames_from).map(|p| p + 1);
+                    if let Some(p) = position {
+                        new_frames_from = new_frames_from.max(p + 1);
+                    }
+                    self.controller().frame_received(&frame);
+                    let output = self.main_loop(&mut frame, &mut video_outputs, &mut text_exporter, playback.as_ref(), replayed);
+                    if let Some(controls) = &mut playback {
+                        if self.wait_for_playback(controls, &frame.image, &output, read_at) {
+                            break;
+                        }
+                    }
+                }
+                Ok(false) => {
+                    break;
                 }
-                _ => {
+                Err(e) => {
+                    self.publish(FollowerEvent::Error(format!("Couldn't get the next frame: {}", e)));
                     break;
                 }
             }
-            frame_num += 1;
         }
 
         destroy_all_windows().unwrap();
-        self.controller.land();
-        self.controller.shutdown();
+        if !self.landed {
+            self.controller().land();
+        }
+        self.controller().shutdown();
+        for e in video_outputs.finish() {
+            self.publish(FollowerEvent::Error(e));
+        }
+        self.finish_exports(text_exporter);
+        self.publish(FollowerEvent::StateChanged(FollowerState::Stopped));
+    }
+}
+
+/// The settings of the video outputs, which are handed to the presenting thread in pipelined mode.
+#[derive(Clone)]
+struct PresentSettings {
+    save_to_file: Option<String>,
+    save_raw_to_file: Option<String>,
+    video_export_options: VideoExportOptions,
+    show_video: bool,
+    stream_video: Option<u16>,
+    stream_video_address: String,
+}
+
+impl PresentSettings {
+    /// Returns true if the frames go anywhere.
+    fn is_needed(&self) -> bool {
+        self.save_to_file.is_some() || self.save_raw_to_file.is_some() || self.show_video || self.stream_video.is_some()
+    }
+}
+
+/// The places the video goes to, besides the highgui window.
+struct VideoOutputs {
+    video_exporter: VideoExporter,
+    /// How many times the export options changed. The files started after a change get this number,
+    /// so the ones already written aren't overwritten.
+    video_segment: usize,
+    mjpeg_server: Option<MjpegServer>,
+    /// The address and port the MJPEG server couldn't be started on, it isn't tried again until
+    /// they change in the settings.
+    failed_mjpeg_address: Option<(String, u16)>,
+    /// The number of frames the VideoExporter dropped when it was last reported.
+    reported_dropped: usize,
+    /// The threads finishing the files of the previous VideoExporters, and the channel of their
+    /// errors.
+    retiring: Vec<thread::JoinHandle<()>>,
+    retired_error_sender: Sender<String>,
+    retired_error_receiver: Receiver<String>,
+}
+
+impl VideoOutputs {
+    fn new() -> VideoOutputs {
+        let (retired_error_sender, retired_error_receiver) = channel();
+        VideoOutputs {
+            video_exporter: VideoExporter::new(),
+            video_segment: 0,
+            mjpeg_server: None,
+            failed_mjpeg_address: None,
+            reported_dropped: 0,
+            retiring: Vec::new(),
+            retired_error_sender,
+            retired_error_receiver,
+        }
+    }
+
+    /// Starts a new VideoExporter if the options in the settings changed. The files of the previous
+    /// one are finished on another thread, so the frames aren't held up by the rest of its queue,
+    /// and the new one writes new files, numbered after the change (for example video_1.mp4).
+    fn update_video_exporter(&mut self, options: &VideoExportOptions) {
+        if self.video_exporter.get_options() != options {
+            let mut retired = std::mem::replace(&mut self.video_exporter, VideoExporter::with_options(options.clone()));
+            let errors = self.retired_error_sender.clone();
+            self.retiring.retain(|join_handle| !join_handle.is_finished());
+            self.retiring.push(thread::spawn(move || {
+                for e in retired.finish() {
+                    let _ = errors.send(e);
+                }
+            }));
+            self.video_segment += 1;
+            self.reported_dropped = 0;
+        }
+    }
+
+    /// Returns the errors of the current and the previous VideoExporters since the last call.
+    fn get_errors(&self) -> Vec<String> {
+        let mut errors = self.video_exporter.get_errors();
+        errors.extend(self.retired_error_receiver.try_iter());
+        errors
+    }
+
+    /// Waits for the files of every VideoExporter to be finished, and returns the errors that
+    /// weren't read yet.
+    fn finish(&mut self) -> Vec<String> {
+        let mut errors = self.video_exporter.finish();
+        for join_handle in self.retiring.drain(..) {
+            let _ = join_handle.join();
+        }
+        errors.extend(self.retired_error_receiver.try_iter());
+        errors
+    }
+
+    /// Saves the frame into the file of the current export options.
+    fn save_frame(&mut self, filename: &str, img: &Mat, timestamp: f64) {
+        let filename = numbered_filename(filename, self.video_segment);
+        self.video_exporter.save_timed_frame(&filename, img, timestamp);
+    }
+
+    /// Starts, stops or moves the MJPEG server if the address or the port in the settings changed.
+    /// Returns the error if the server couldn't be started, in which case the video isn't streamed.
+    fn update_mjpeg_server(&mut self, address: &str, stream_video: Option<u16>) -> Option<String> {
+        let current = self.mjpeg_server.as_ref().map(|server| (String::from(server.get_address()), server.get_port()));
+        let wanted = stream_video.map(|port| (String::from(address), port));
+        if current == wanted || (self.failed_mjpeg_address.is_some() && self.failed_mjpeg_address == wanted) {
+            return None;
+        }
+        self.mjpeg_server = None;
+        self.failed_mjpeg_address = None;
+        let port = stream_video?;
+        match MjpegServer::bind(address, port) {
+            Ok(server) => {
+                self.mjpeg_server = Some(server);
+                None
+            }
+            Err(e) => {
+                self.failed_mjpeg_address = wanted;
+                Some(format!("Couldn't start the MJPEG server on {}:{}: {}", address, port, e))
+            }
+        }
     }
 }
 
+/// Shows the image in the highgui window. If an overlay is given, it is written on a copy of the
+/// image, and the keys are left to be handled by the caller.
+fn show_image(img: &Mat, overlay: Option<&[String]>) {
+    match overlay {
+        Some(rows) => {
+            let mut shown = img.clone().unwrap();
+            draw_overlay(&mut shown, rows);
+            imshow("Image", &shown).unwrap();
+        }
+        None => {
+            imshow("Image", img).unwrap();
+            wait_key(3).unwrap();
+        }
+    }
+}
+
+/// Saves the raw image, applies the markers to it, saves it to the video file, shows it (with the
+/// overlay, if it is given) and streams it, if it is needed. Returns the events of the video outputs
+/// (their errors, and the number of dropped frames if it grew).
+fn present_frame(img: &mut Mat, timestamp: f64, mut m_d: MarkerDrawer, p_c: &PointConverter, settings: &PresentSettings, overlay: Option<&[String]>, video_outputs: &mut VideoOutputs) -> Vec<FollowerEvent> {
+    video_outputs.update_video_exporter(&settings.video_export_options);
+
+    // Save the clean image to video file
+    if let Some(filename) = &settings.save_raw_to_file {
+        video_outputs.save_frame(filename, img, timestamp);
+    }
+
+    // Applies the given markers to the image.
+    m_d.draw_on_image(img, p_c);
+
+    // Save to video file
+    if let Some(filename) = &settings.save_to_file {
+        video_outputs.save_frame(filename, img, timestamp);
+    }
+
+    // Stream over HTTP
+    let mut errors = Vec::new();
+    errors.extend(video_outputs.update_mjpeg_server(&settings.stream_video_address, settings.stream_video));
+    if let Some(server) = &video_outputs.mjpeg_server {
+        if let Err(e) = server.send_frame(img) {
+            errors.push(format!("Couldn't encode the frame for the MJPEG stream: {}", e));
+        }
+    }
+
+    // Show video file
+    if settings.show_video {
+        show_image(img, overlay);
+    }
+
+    errors.extend(video_outputs.get_errors());
+    let mut events: Vec<FollowerEvent> = errors.into_iter().map(FollowerEvent::Error).collect();
+    let dropped = video_outputs.video_exporter.get_dropped();
+    if dropped > video_outputs.reported_dropped {
+        video_outputs.reported_dropped = dropped;
+        events.push(FollowerEvent::Dropped { output: String::from("video"), count: dropped });
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::f64::consts::PI;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    use opencv::core::{Mat, Point};
+
+    use crate::traits::{Detector, Filter, FrameSource};
+    use crate::controllers::recording_controller::RecordingController;
+    use crate::models::frame::Frame;
+    use crate::models::geometric_point::GeometricPoint;
+    use crate::utils::marker_drawer::MarkerDrawer;
+    use crate::utils::point_converter::PointConverter;
+
+    use super::{HatFollower, HatFollowerSettings};
+
+    /// Detects the given positions, one per frame.
+    struct StubDetector {
+        positions: VecDeque<Option<GeometricPoint>>,
+        position: Option<GeometricPoint>,
+    }
+
+    impl Detector for StubDetector {
+        fn get_detected_position(&self) -> Option<GeometricPoint> {
+            self.position.clone()
+        }
+        fn get_detected_angle(&self) -> Option<f64> {
+            self.position.as_ref().map(|_| PI / 2.0)
+        }
+        fn get_detection_certainty(&self) -> f64 {
+            0.8
+        }
+        fn detect_new_position(&mut self, _img: &Mat, _old_pos: Option<Point>, _p_c: &PointConverter) {
+            self.position = self.positions.pop_front().flatten();
+        }
+        fn draw_on_image(&self, _m_d: &mut MarkerDrawer) {}
+    }
+
+    /// Estimates the detected position with a fixed velocity, and records the time between the
+    /// updates.
+    struct StubFilter {
+        point: Option<GeometricPoint>,
+        angle: f64,
+        dts: Vec<f64>,
+    }
+
+    impl Filter for StubFilter {
+        fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, _cert: f64, dt: f64) {
+            self.point = point;
+            self.angle = angle.unwrap_or(self.angle);
+            self.dts.push(dt);
+        }
+        fn get_estimated_position(&self) -> Option<GeometricPoint> {
+            self.point.clone()
+        }
+        fn get_estimated_angle(&self) -> f64 {
+            self.angle
+        }
+        fn get_estimated_vx(&self) -> f64 {
+            2.0
+        }
+        fn get_estimated_vy(&self) -> f64 {
+            -1.0
+        }
+        fn get_estimation_certainty(&self) -> f64 {
+            0.5
+        }
+        fn draw_on_image(&self, _m_d: &mut MarkerDrawer) {}
+    }
+
+    struct StubSource;
+
+    impl FrameSource for StubSource {
+        fn get_video_height(&self) -> usize {
+            368
+        }
+        fn get_video_width(&self) -> usize {
+            640
+        }
+        fn get_next_frame(&mut self, _frame: &mut Frame) -> opencv::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    fn follower(positions: Vec<Option<GeometricPoint>>) -> HatFollower<StubDetector, StubSource, RecordingController, StubFilter> {
+        HatFollower::new(
+            StubDetector { positions: positions.into_iter().collect(), position: None },
+            StubSource,
+            RecordingController::new(),
+            StubFilter { point: None, angle: PI / 2.0, dts: Vec::new() },
+            HatFollowerSettings::silent(),
+            None,
+        )
+    }
+
+    fn frame(timestamp: f64) -> Frame {
+        Frame { image: Mat::default().unwrap(), timestamp }
+    }
+
+    #[test]
+    fn step_should_return_the_detection_and_the_estimation() {
+        let mut sut = follower(vec![Some(GeometricPoint::new(8, -6))]);
+
+        let output = sut.step(&frame(1.0));
+
+        assert_eq!(output.frame_num, 1);
+        assert_eq!(output.dt, 0.0);
+        assert_eq!(output.detected_position.map(|p| (p.x, p.y)), Some((8, -6)));
+        assert_eq!(output.detected_angle, Some(PI / 2.0));
+        assert_eq!(output.detection_certainty, 0.8);
+        assert_eq!(output.estimated_position.map(|p| (p.x, p.y)), Some((8, -6)));
+        assert_eq!(output.estimated_angle, PI / 2.0);
+        assert_eq!((output.estimated_vx, output.estimated_vy), (2.0, -1.0));
+        assert_eq!(output.estimation_certainty, 0.5);
+        assert_eq!(output.command, Some((0.8, -0.6, 0.0, 0.0)))
+    }
+
+    #[test]
+    fn replayed_frame_should_not_publish_anything() {
+        let position = Some(GeometricPoint::new(100, 0));
+        let mut sut = follower(vec![position.clone(), position.clone(), position]);
+        let ground_station = UdpSocket::bind("127.0.0.1:0").unwrap();
+        ground_station.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        sut.settings.stream_telemetry = Some(ground_station.local_addr().unwrap().to_string());
+        let events = sut.subscribe();
+        let mut buffer = [0u8; 1024];
+
+        sut.step(&frame(1.0));
+        assert!(ground_station.recv(&mut buffer).is_ok());
+        assert_eq!(events.try_iter().count(), 2);
+
+        // Stepping back to the first frame.
+        let replayed = sut.process_frame(&frame(1.0), Some(1));
+        assert!(ground_station.recv(&mut buffer).is_err());
+        assert_eq!(events.try_iter().count(), 0);
+        assert!(replayed.frame_num == 1 && replayed.command.is_none());
+
+        assert_eq!(sut.step(&frame(1.04)).frame_num, 2)
+    }
+
+    #[test]
+    fn step_should_only_return_changed_commands() {
+        let position = Some(GeometricPoint::new(100, 0));
+        let mut sut = follower(vec![position.clone(), position, None]);
+
+        let first = sut.step(&frame(1.0));
+        let second = sut.step(&frame(1.04));
+        let third = sut.step(&frame(1.1));
+
+        assert_eq!((first.frame_num, second.frame_num, third.frame_num), (1, 2, 3));
+        assert!(first.command.is_some());
+        assert!(second.command.is_none());
+        assert_eq!(third.detected_position.map(|p| (p.x, p.y)), None);
+        assert_eq!(third.command, Some((0.0, 0.0, 0.0, 0.0)));
+        let dts = &sut.filter.dts;
+        assert_eq!(dts.len(), 3);
+        assert!(dts[0] == 0.0 && (dts[1] - 0.04).abs() < 1e-9 && (dts[2] - 0.06).abs() < 1e-9)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::models::ground_point::GroundPoint;
+
+/// Number of iterations used when removing the lens distortion from a point.
+const UNDISTORT_ITERATIONS: usize = 20;
+
+/// The intrinsic parameters of a camera (pinhole model) and the distortion of its lens. With it
+/// points of the image can be converted to directions, and with a known altitude to metres on the
+/// ground.
+///
+/// The parameters are the same that OpenCV uses: fx, fy are the focal lengths and cx, cy is the
+/// principal point in pixels; the distortion coefficients are (k1, k2, p1, p2, k3).
+#[derive(Clone, Debug)]
+pub struct CameraModel {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    pub distortion: [f64; 5],
+}
+
+impl CameraModel {
+    /// Creates a new CameraModel from the intrinsics matrix and the distortion coefficients.
+    ///
+    /// Usage:
+    /// ```
+    /// use rust_drone_follow::models::camera_model::CameraModel;
+    /// // ...
+    /// # fn main() {
+    ///     let camera_model = CameraModel::new(520.0, 520.0, 320.0, 184.0, [0.0; 5]);
+    /// # }
+    /// ```
+    pub fn new(fx: f64, fy: f64, cx: f64, cy: f64, distortion: [f64; 5]) -> CameraModel {
+        CameraModel {
+            fx,
+            fy,
+            cx,
+            cy,
+            distortion,
+        }
+    }
+
+    /// Takes a point in the coordinate system of an image (in pixels, but it doesn't need to be
+    /// rounded) and returns its normalized coordinates (x / z, y / z of the direction in which the
+    /// point is seen) with the distortion of the lens removed.
+    pub fn undistort(&self, x: f64, y: f64) -> (f64, f64) {
+        let [k1, k2, p1, p2, k3] = self.distortion;
+        let x0 = (x - self.cx) / self.fx;
+        let y0 = (y - self.cy) / self.fy;
+
+        let (mut x, mut y) = (x0, y0);
+        for _ in 0..UNDISTORT_ITERATIONS {
+            let r2 = x * x + y * y;
+            let icdist = 1.0 / (1.0 + ((k3 * r2 + k2) * r2 + k1) * r2);
+            let delta_x = 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x);
+            let delta_y = p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y;
+            x = (x0 - delta_x) * icdist;
+            y = (y0 - delta_y) * icdist;
+        }
+
+        (x, y)
+    }
+
+    /// The inverse of undistort: takes normalized coordinates and returns where the point appears
+    /// on the image (in pixels) because of the lens distortion.
+    pub fn distort(&self, x: f64, y: f64) -> (f64, f64) {
+        let [k1, k2, p1, p2, k3] = self.distortion;
+        let r2 = x * x + y * y;
+        let radial = 1.0 + ((k3 * r2 + k2) * r2 + k1) * r2;
+        let xd = x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x);
+        let yd = y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y;
+
+        (xd * self.fx + self.cx, yd * self.fy + self.cy)
+    }
+
+    /// Takes a point in the coordinate system of an image and returns where it is on the ground, if
+    /// the camera is at the given altitude (in metres) and points straight down.
+    pub fn image_to_ground(&self, x: f64, y: f64, altitude: f64) -> GroundPoint {
+        let (x, y) = self.undistort(x, y);
+        // The y axis of the image points downwards.
+        GroundPoint::new(x * altitude, - y * altitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CameraModel;
+
+    fn camera() -> CameraModel {
+        CameraModel::new(500.0, 500.0, 320.0, 240.0, [-0.2, 0.05, 0.001, -0.002, 0.0])
+    }
+
+    #[test]
+    fn principal_point_should_be_below_camera() {
+        let sut = camera();
+        let point = sut.image_to_ground(320.0, 240.0, 2.0);
+
+        assert!(point.x.abs() < 1e-9 && point.y.abs() < 1e-9)
+    }
+
+    #[test]
+    fn undistort_should_invert_distort() {
+        let sut = camera();
+        let (x, y) = sut.distort(0.3, -0.2);
+        let (ux, uy) = sut.undistort(x, y);
+
+        assert!((ux - 0.3).abs() < 1e-6 && (uy + 0.2).abs() < 1e-6)
+    }
+
+    #[test]
+    fn ground_distance_should_grow_with_altitude() {
+        let sut = CameraModel::new(500.0, 500.0, 320.0, 240.0, [0.0; 5]);
+        let point = sut.image_to_ground(570.0, 240.0, 2.0);
+
+        assert!((point.x - 1.0).abs() < 1e-9 && point.y.abs() < 1e-9)
+    }
+}
@@ -0,0 +1,18 @@
+use opencv::core::*;
+
+/// A video frame together with the time it was captured at.
+pub struct Frame {
+    pub image: Mat,
+    /// Capture time in seconds, measured from the start of the video stream.
+    pub timestamp: f64,
+}
+
+impl Frame {
+    /// Returns an empty frame, that can be filled by FrameSource::get_next_frame.
+    pub fn new() -> Frame {
+        Frame {
+            image: Mat::zeros_size(Size::new(1,1), CV_8U).unwrap().to_mat().unwrap(),
+            timestamp: 0.0,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+/// The horizontal shape of a geofence, in metres, in the local coordinate system of the drone
+/// (origin at the takeoff point, x towards the right and y towards the front of the drone at
+/// takeoff).
+#[derive(Clone)]
+pub enum GeofenceShape {
+    /// A circle around the takeoff point with the given radius.
+    Circle(f64),
+    /// A polygon given by its corners in order (either direction).
+    Polygon(Vec<(f64, f64)>),
+}
+
+/// The area the drone is allowed to fly in: a horizontal shape and a maximum altitude.
+#[derive(Clone)]
+pub struct Geofence {
+    pub shape: GeofenceShape,
+    /// Maximum altitude in metres.
+    pub max_altitude: f64,
+}
+
+impl Geofence {
+    pub fn new(shape: GeofenceShape, max_altitude: f64) -> Geofence {
+        Geofence {
+            shape,
+            max_altitude,
+        }
+    }
+
+    /// Returns true if the point is inside the horizontal shape.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        match &self.shape {
+            GeofenceShape::Circle(radius) => (x.powi(2) + y.powi(2)).sqrt() <= *radius,
+            GeofenceShape::Polygon(corners) => {
+                // Ray casting: counts the edges crossed by a ray going towards the right.
+                let mut inside = false;
+                for (i, (ax, ay)) in corners.iter().enumerate() {
+                    let (bx, by) = corners[(i + 1) % corners.len()];
+                    if (*ay > y) != (by > y) && x < ax + (y - ay) * (bx - ax) / (by - ay) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+
+    /// Returns the distance of the point from the border of the horizontal shape, and the unit
+    /// vector pointing outwards (away from the inside of the shape) at the closest border point.
+    pub fn distance_to_border(&self, x: f64, y: f64) -> (f64, (f64, f64)) {
+        match &self.shape {
+            GeofenceShape::Circle(radius) => {
+                let d = (x.powi(2) + y.powi(2)).sqrt();
+                let outward = if d > 0.0 { (x / d, y / d) } else { (0.0, 1.0) };
+                ((radius - d).abs(), outward)
+            }
+            GeofenceShape::Polygon(corners) => {
+                let (mut min_d, mut closest) = (f64::MAX, (x, y));
+                for (i, (ax, ay)) in corners.iter().enumerate() {
+                    let (bx, by) = corners[(i + 1) % corners.len()];
+                    let (ex, ey) = (bx - ax, by - ay);
+                    let length = ex.powi(2) + ey.powi(2);
+                    let t = if length > 0.0 { (((x - ax) * ex + (y - ay) * ey) / length).min(1.0).max(0.0) } else { 0.0 };
+                    let (qx, qy) = (ax + t * ex, ay + t * ey);
+                    let d = ((x - qx).powi(2) + (y - qy).powi(2)).sqrt();
+                    if d < min_d {
+                        min_d = d;
+                        closest = (qx, qy);
+                    }
+                }
+                if min_d == 0.0 {
+                    return (0.0, (0.0, 0.0));
+                }
+                // From inside the closest border point is outwards, from outside it is inwards.
+                let sign = if self.contains(x, y) { 1.0 } else { -1.0 };
+                (min_d, (sign * (closest.0 - x) / min_d, sign * (closest.1 - y) / min_d))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Geofence, GeofenceShape};
+
+    fn square() -> Geofence {
+        Geofence::new(GeofenceShape::Polygon(vec![(-10.0, -10.0), (10.0, -10.0), (10.0, 10.0), (-10.0, 10.0)]), 5.0)
+    }
+
+    #[test]
+    fn circle_should_contain_points_inside_radius() {
+        let sut = Geofence::new(GeofenceShape::Circle(5.0), 5.0);
+
+        assert!(sut.contains(3.0, 3.0) && !sut.contains(4.0, 4.0))
+    }
+
+    #[test]
+    fn polygon_should_contain_points_inside() {
+        let sut = square();
+
+        assert!(sut.contains(9.0, -9.0) && !sut.contains(11.0, 0.0))
+    }
+
+    #[test]
+    fn polygon_border_direction_should_point_outwards() {
+        let sut = square();
+        let (d_in, out_in) = sut.distance_to_border(8.0, 0.0);
+        let (d_out, out_out) = sut.distance_to_border(12.0, 0.0);
+
+        assert!((d_in - 2.0).abs() < 1e-9 && (out_in.0 - 1.0).abs() < 1e-9);
+        assert!((d_out - 2.0).abs() < 1e-9 && (out_out.0 - 1.0).abs() < 1e-9)
+    }
+}
@@ -3,6 +3,7 @@
 ///
 /// These points are points from a descartes coordinate system, and you are able to use most
 /// mathematical formulas (formulae?).
+#[derive(PartialEq, Debug)]
 pub struct GeometricPoint {
     pub x: i32,
     pub y: i32
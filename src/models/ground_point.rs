@@ -0,0 +1,19 @@
+/// A point on the ground plane in metres, relative to the point directly below the camera.
+///
+/// x grows towards the right side of the image, y grows towards the top of the image, the same way
+/// as in the descartes coordinate-system of GeometricPoint.
+#[derive(Clone)]
+pub struct GroundPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl GroundPoint {
+    pub fn new(x: f64, y: f64) -> GroundPoint {
+        GroundPoint { x, y }
+    }
+    /// Distance from O(0, 0) in metres
+    pub fn d(&self) -> f64 {
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
+}
@@ -1,7 +1,25 @@
+pub mod camera_model;
+pub mod frame;
+pub mod geofence;
 pub mod geometric_point;
+pub mod ground_point;
 pub mod hat;
 pub mod lab_color;
+pub mod recorded_frame;
+pub mod rigid_motion;
+pub mod state_record;
+pub mod telemetry;
+pub mod telemetry_record;
 
+pub use camera_model::CameraModel;
+pub use frame::Frame;
+pub use geofence::{Geofence, GeofenceShape};
 pub use geometric_point::GeometricPoint;
+pub use ground_point::GroundPoint;
 pub use hat::Hat;
 pub use lab_color::LabColor;
+pub use recorded_frame::RecordedFrame;
+pub use rigid_motion::RigidMotion;
+pub use state_record::StateRecord;
+pub use telemetry::Telemetry;
+pub use telemetry_record::TelemetryRecord;
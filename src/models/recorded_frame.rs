@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use crate::StepOutput;
+use crate::models::geometric_point::GeometricPoint;
+use crate::models::telemetry::Telemetry;
+
+/// Everything that was recorded about a frame in a session (see SessionRecorder): the file of the
+/// raw image, its capture time, the telemetry of the drone, and the output of the HatFollower.
+pub struct RecordedFrame {
+    /// Capture time of the frame in seconds.
+    pub timestamp: f64,
+    /// Path of the raw image, relative to the session directory.
+    pub image_file: String,
+    pub telemetry: Telemetry,
+    pub output: StepOutput,
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_else(|| String::from("-"))
+}
+
+fn parse<T: FromStr>(value: Option<&str>) -> Option<T> {
+    value.and_then(|v| v.parse::<T>().ok())
+}
+
+fn parse_optional<T: FromStr>(value: Option<&str>) -> Option<Option<T>> {
+    match value {
+        Some("-") => Some(None),
+        value => parse::<T>(value).map(Some),
+    }
+}
+
+impl RecordedFrame {
+    /// Returns the frame as a line of space separated values (without a line break), missing
+    /// values are written as "-":
+    ///
+    /// frame_num dt timestamp image_file detected_x detected_y detected_angle detection_certainty
+    /// estimated_x estimated_y estimated_angle estimated_vx estimated_vy estimation_certainty
+    /// left_right back_front down_up turn_left_right pitch roll yaw altitude battery
+    pub fn to_line(&self) -> String {
+        let o = &self.output;
+        let t = &self.telemetry;
+        let command = match o.command {
+            Some((lr, bf, du, turn)) => format!("{} {} {} {}", lr, bf, du, turn),
+            None => String::from("- - - -"),
+        };
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            o.frame_num, o.dt, self.timestamp, self.image_file,
+            optional(&o.detected_position.as_ref().map(|p| p.x)), optional(&o.detected_position.as_ref().map(|p| p.y)),
+            optional(&o.detected_angle), o.detection_certainty,
+            optional(&o.estimated_position.as_ref().map(|p| p.x)), optional(&o.estimated_position.as_ref().map(|p| p.y)),
+            o.estimated_angle, o.estimated_vx, o.estimated_vy, o.estimation_certainty,
+            command,
+            optional(&t.pitch), optional(&t.roll), optional(&t.yaw), optional(&t.altitude), optional(&t.battery)
+        )
+    }
+
+    /// Reads a line written by to_line. Returns None if the line is not valid.
+    pub fn from_line(line: &str) -> Option<RecordedFrame> {
+        let mut values = line.split_whitespace();
+        let frame_num = parse::<usize>(values.next())?;
+        let dt = parse::<f64>(values.next())?;
+        let timestamp = parse::<f64>(values.next())?;
+        let image_file = String::from(values.next()?);
+        let detected_x = parse_optional::<i32>(values.next())?;
+        let detected_y = parse_optional::<i32>(values.next())?;
+        let detected_angle = parse_optional::<f64>(values.next())?;
+        let detection_certainty = parse::<f64>(values.next())?;
+        let estimated_x = parse_optional::<i32>(values.next())?;
+        let estimated_y = parse_optional::<i32>(values.next())?;
+        let estimated_angle = parse::<f64>(values.next())?;
+        let estimated_vx = parse::<f64>(values.next())?;
+        let estimated_vy = parse::<f64>(values.next())?;
+        let estimation_certainty = parse::<f64>(values.next())?;
+        let mut command = [None; 4];
+        for value in command.iter_mut() {
+            *value = parse_optional::<f64>(values.next())?;
+        }
+        let mut telemetry = Telemetry::new();
+        telemetry.pitch = parse_optional::<f64>(values.next())?;
+        telemetry.roll = parse_optional::<f64>(values.next())?;
+        telemetry.yaw = parse_optional::<f64>(values.next())?;
+        telemetry.altitude = parse_optional::<f64>(values.next())?;
+        telemetry.battery = parse_optional::<f64>(values.next())?;
+
+        let point = |x: Option<i32>, y: Option<i32>| x.and_then(|x| y.map(|y| GeometricPoint::new(x, y)));
+        Some(RecordedFrame {
+            timestamp,
+            image_file,
+            telemetry,
+            output: StepOutput {
+                frame_num,
+                dt,
+                detected_position: point(detected_x, detected_y),
+                detected_angle,
+                detection_certainty,
+                estimated_position: point(estimated_x, estimated_y),
+                estimated_angle,
+                estimated_vx,
+                estimated_vy,
+                estimation_certainty,
+                command: match command {
+                    [Some(lr), Some(bf), Some(du), Some(turn)] => Some((lr, bf, du, turn)),
+                    _ => None,
+                },
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StepOutput;
+    use crate::models::geometric_point::GeometricPoint;
+    use crate::models::telemetry::Telemetry;
+
+    use super::RecordedFrame;
+
+    fn recorded_frame(command: Option<(f64, f64, f64, f64)>) -> RecordedFrame {
+        let mut telemetry = Telemetry::new();
+        telemetry.altitude = Some(2.5);
+        RecordedFrame {
+            timestamp: 0.4,
+            image_file: String::from("frames/000012.png"),
+            telemetry,
+            output: StepOutput {
+                frame_num: 12,
+                dt: 0.033,
+                detected_position: Some(GeometricPoint::new(5, -7)),
+                detected_angle: None,
+                detection_certainty: 0.6,
+                estimated_position: None,
+                estimated_angle: 0.25,
+                estimated_vx: -1.5,
+                estimated_vy: 2.0,
+                estimation_certainty: 0.5,
+                command,
+            },
+        }
+    }
+
+    #[test]
+    fn line_should_round_trip() {
+        let line = recorded_frame(Some((0.1, 0.0, 0.0, -0.2))).to_line();
+        let sut = RecordedFrame::from_line(&line).unwrap();
+
+        assert_eq!(sut.to_line(), line);
+        assert!(sut.output.detected_position == Some(GeometricPoint::new(5, -7)) && sut.output.estimated_position.is_none());
+        assert!(sut.output.command == Some((0.1, 0.0, 0.0, -0.2)) && sut.telemetry.altitude == Some(2.5))
+    }
+
+    #[test]
+    fn missing_command_should_be_read_as_none() {
+        let line = recorded_frame(None).to_line();
+
+        assert!(RecordedFrame::from_line(&line).unwrap().output.command.is_none())
+    }
+
+    #[test]
+    fn invalid_line_should_give_none() {
+        assert!(RecordedFrame::from_line("12 0.033 0.4").is_none())
+    }
+}
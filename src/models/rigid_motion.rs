@@ -0,0 +1,19 @@
+/// A rotation around the origin (in radians) followed by a translation. It describes how a set of
+/// points moved between two frames.
+#[derive(Clone)]
+pub struct RigidMotion {
+    pub dx: f64,
+    pub dy: f64,
+    pub angle: f64,
+}
+
+impl RigidMotion {
+    /// Returns the position of the given point after the motion.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.angle.sin_cos();
+        (
+            x * cos - y * sin + self.dx,
+            x * sin + y * cos + self.dy
+        )
+    }
+}
@@ -0,0 +1,154 @@
+use crate::follower_event::FollowerState;
+use crate::models::geometric_point::GeometricPoint;
+
+use crate::utils::json::{json_object, JsonValue};
+
+/// Version of the columns below, it should be increased whenever they change.
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+
+/// The columns of the CSV export, and the keys of the JSON Lines export.
+pub const STATE_COLUMNS: [&str; 19] = [
+    "timestamp", "frame_num", "dt", "state",
+    "detected_x", "detected_y", "detected_angle", "detection_certainty",
+    "estimated_x", "estimated_y", "estimated_angle", "estimated_vx", "estimated_vy", "estimation_certainty",
+    "command_left_right", "command_back_front", "command_down_up", "command_turn_left_right", "command_sent",
+];
+
+/// The full state of the HatFollower after processing a frame, saved by the StateExporter for
+/// offline analysis. Positions are in the descartes coordinate system of the image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StateRecord {
+    /// Capture time of the frame in seconds.
+    pub timestamp: f64,
+    pub frame_num: usize,
+    /// Time since the previous frame in seconds.
+    pub dt: f64,
+    pub state: FollowerState,
+    pub detected_position: Option<GeometricPoint>,
+    pub detected_angle: Option<f64>,
+    pub detection_certainty: f64,
+    pub estimated_position: Option<GeometricPoint>,
+    pub estimated_angle: f64,
+    /// Estimated velocity of the hat in pixels/second.
+    pub estimated_vx: f64,
+    pub estimated_vy: f64,
+    pub estimation_certainty: f64,
+    /// The command calculated for the frame (left_right, back_front, down_up, turn_left_right),
+    /// None if the follower was paused or landed.
+    pub command: Option<(f64, f64, f64, f64)>,
+    /// Whether the command was sent to the drone (it is only sent if it changed more than
+    /// min_change).
+    pub command_sent: bool,
+}
+
+impl StateRecord {
+    /// Returns the values in the order of STATE_COLUMNS, missing values are Null.
+    fn values(&self) -> Vec<JsonValue> {
+        let (detected_x, detected_y) = coords(&self.detected_position);
+        let (estimated_x, estimated_y) = coords(&self.estimated_position);
+        let (lr, bf, du, turn) = match self.command {
+            Some((lr, bf, du, turn)) => (lr.into(), bf.into(), du.into(), turn.into()),
+            None => (JsonValue::Null, JsonValue::Null, JsonValue::Null, JsonValue::Null),
+        };
+        vec![
+            self.timestamp.into(), JsonValue::Integer(self.frame_num as i64), self.dt.into(), JsonValue::String(format!("{:?}", self.state)),
+            detected_x, detected_y, self.detected_angle.into(), self.detection_certainty.into(),
+            estimated_x, estimated_y, self.estimated_angle.into(), self.estimated_vx.into(), self.estimated_vy.into(),
+            self.estimation_certainty.into(),
+            lr, bf, du, turn, JsonValue::Bool(self.command_sent),
+        ]
+    }
+
+    /// Returns the header of the CSV export: a comment line with the schema version, and the names
+    /// of the columns.
+    pub fn csv_header() -> String {
+        format!("# schema_version {}\n{}", STATE_SCHEMA_VERSION, STATE_COLUMNS.join(","))
+    }
+
+    /// Returns the record as a CSV row (without a line break), missing values are left empty.
+    pub fn to_csv(&self) -> String {
+        self.values().into_iter()
+            .map(|v| match v {
+                JsonValue::Null => String::new(),
+                JsonValue::String(text) => text,
+                // NaN and inf can be read from CSV, unlike from JSON.
+                JsonValue::Number(number) => number.to_string(),
+                v => v.to_json(),
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    /// Returns the header of the JSON Lines export: an object with the schema version and the
+    /// names of the columns.
+    pub fn json_header() -> String {
+        let columns = STATE_COLUMNS.iter().map(|c| format!("\"{}\"", c)).collect::<Vec<String>>();
+        format!("{{\"schema_version\":{},\"columns\":[{}]}}", STATE_SCHEMA_VERSION, columns.join(","))
+    }
+
+    /// Returns the record as a single line JSON object, missing values and values that aren't
+    /// finite are null.
+    pub fn to_json(&self) -> String {
+        json_object(STATE_COLUMNS.iter().copied().zip(self.values()).collect()).to_json()
+    }
+}
+
+fn coords(point: &Option<GeometricPoint>) -> (JsonValue, JsonValue) {
+    match point {
+        Some(p) => (JsonValue::Integer(p.x as i64), JsonValue::Integer(p.y as i64)),
+        None => (JsonValue::Null, JsonValue::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::follower_event::FollowerState;
+    use crate::models::geometric_point::GeometricPoint;
+
+    use super::{StateRecord, STATE_COLUMNS};
+
+    fn record() -> StateRecord {
+        StateRecord {
+            timestamp: 1.5,
+            frame_num: 45,
+            dt: 0.033,
+            state: FollowerState::Following,
+            detected_position: None,
+            detected_angle: None,
+            detection_certainty: 0.0,
+            estimated_position: Some(GeometricPoint::new(-10, 29)),
+            estimated_angle: 0.3,
+            estimated_vx: 12.5,
+            estimated_vy: -3.0,
+            estimation_certainty: 0.7,
+            command: Some((0.1, -0.2, 0.0, 0.05)),
+            command_sent: true,
+        }
+    }
+
+    #[test]
+    fn csv_row_should_match_the_header() {
+        let sut = record().to_csv();
+
+        assert_eq!(sut.split(',').count(), STATE_COLUMNS.len());
+        assert_eq!(sut, "1.5,45,0.033,Following,,,,0,-10,29,0.3,12.5,-3,0.7,0.1,-0.2,0,0.05,true")
+    }
+
+    #[test]
+    fn json_should_contain_nulls_for_missing_values() {
+        let sut = record().to_json();
+
+        assert!(sut.starts_with("{\"timestamp\":1.5,\"frame_num\":45,\"dt\":0.033,\"state\":\"Following\",\"detected_x\":null,"));
+        assert!(sut.ends_with("\"command_sent\":true}"))
+    }
+
+    #[test]
+    fn json_should_contain_null_for_non_finite_values() {
+        let mut sut = record();
+        sut.dt = f64::NAN;
+        sut.estimated_vx = f64::NEG_INFINITY;
+
+        let json = sut.to_json();
+        assert!(json.contains("\"dt\":null,") && json.contains("\"estimated_vx\":null,"))
+    }
+}
@@ -0,0 +1,30 @@
+/// Information reported by the drone about its own state. Every field is optional, since not every
+/// drone (or controller) is able to provide all of them.
+///
+/// Angles are in radians.
+#[derive(Clone)]
+pub struct Telemetry {
+    /// Rotation around the side axis, positive when the nose of the drone is up.
+    pub pitch: Option<f64>,
+    /// Rotation around the front axis, positive when the right side of the drone is down.
+    pub roll: Option<f64>,
+    /// Heading of the drone, positive counter-clockwise (seen from above).
+    pub yaw: Option<f64>,
+    /// Height of the drone above the ground in metres.
+    pub altitude: Option<f64>,
+    /// Remaining charge of the battery, between 0.0 (empty) and 1.0 (full).
+    pub battery: Option<f64>,
+}
+
+impl Telemetry {
+    /// Returns a Telemetry with no information in it.
+    pub fn new() -> Telemetry {
+        Telemetry {
+            pitch: None,
+            roll: None,
+            yaw: None,
+            altitude: None,
+            battery: None,
+        }
+    }
+}
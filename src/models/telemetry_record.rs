@@ -0,0 +1,204 @@
+use std::convert::TryInto;
+
+use crate::follower_event::FollowerState;
+use crate::models::geometric_point::GeometricPoint;
+
+use crate::utils::json::{json_object, JsonValue};
+
+/// The first bytes of every record in the binary format.
+const MAGIC: &[u8; 4] = b"HFT1";
+/// Size of a record in the binary format.
+const BINARY_SIZE: usize = 4 + 8 + 8 + 1 + 4 * 2 + 8 + 4 * 2 + 8 * 8 + 1 + 8;
+
+/// Everything the HatFollower knows about a frame, sent to the ground station by the
+/// TelemetryPublisher. Positions are in the descartes coordinate system of the image.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TelemetryRecord {
+    /// Capture time of the frame in seconds.
+    pub timestamp: f64,
+    pub frame_num: u64,
+    pub detection: Option<GeometricPoint>,
+    pub detection_certainty: f64,
+    pub estimate: Option<GeometricPoint>,
+    pub estimated_angle: f64,
+    /// Estimated velocity of the hat in pixels/second.
+    pub estimated_vx: f64,
+    pub estimated_vy: f64,
+    pub estimation_certainty: f64,
+    /// The last command sent to the drone (left_right, back_front, down_up, turn_left_right).
+    pub command: (f64, f64, f64, f64),
+    pub state: FollowerState,
+    /// Battery level reported by the drone (between 0.0 and 1.0).
+    pub battery: Option<f64>,
+}
+
+impl TelemetryRecord {
+    /// Returns the record as a single line JSON object. Values that aren't finite are null.
+    pub fn to_json(&self) -> String {
+        let point = |p: &Option<GeometricPoint>| match p {
+            Some(p) => json_object(vec![
+                ("x", JsonValue::Integer(p.x as i64)),
+                ("y", JsonValue::Integer(p.y as i64)),
+            ]),
+            None => JsonValue::Null,
+        };
+        let (lr, bf, du, turn) = self.command;
+
+        json_object(vec![
+            ("timestamp", self.timestamp.into()),
+            ("frame_num", JsonValue::Integer(self.frame_num as i64)),
+            ("detection", point(&self.detection)),
+            ("detection_certainty", self.detection_certainty.into()),
+            ("estimate", point(&self.estimate)),
+            ("estimated_angle", self.estimated_angle.into()),
+            ("estimated_vx", self.estimated_vx.into()),
+            ("estimated_vy", self.estimated_vy.into()),
+            ("estimation_certainty", self.estimation_certainty.into()),
+            ("command", JsonValue::Array(vec![lr.into(), bf.into(), du.into(), turn.into()])),
+            ("state", JsonValue::String(format!("{:?}", self.state))),
+            ("battery", self.battery.into()),
+        ]).to_json()
+    }
+
+    /// Returns the record in a compact, fixed size binary format (little endian).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BINARY_SIZE);
+        let flags = self.detection.is_some() as u8
+            | (self.estimate.is_some() as u8) << 1
+            | (self.battery.is_some() as u8) << 2;
+        let coords = |p: &Option<GeometricPoint>| p.as_ref().map(|p| (p.x, p.y)).unwrap_or((0, 0));
+        let (dx, dy) = coords(&self.detection);
+        let (ex, ey) = coords(&self.estimate);
+        let (lr, bf, du, turn) = self.command;
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&self.frame_num.to_le_bytes());
+        bytes.push(flags);
+        bytes.extend_from_slice(&dx.to_le_bytes());
+        bytes.extend_from_slice(&dy.to_le_bytes());
+        bytes.extend_from_slice(&self.detection_certainty.to_le_bytes());
+        bytes.extend_from_slice(&ex.to_le_bytes());
+        bytes.extend_from_slice(&ey.to_le_bytes());
+        for value in [self.estimated_angle, self.estimated_vx, self.estimated_vy, self.estimation_certainty, lr, bf, du, turn].iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.push(state_to_byte(self.state));
+        bytes.extend_from_slice(&self.battery.unwrap_or(0.0).to_le_bytes());
+        bytes
+    }
+
+    /// Reads a record written by to_bytes. Returns None if the bytes are not a valid record.
+    pub fn from_bytes(bytes: &[u8]) -> Option<TelemetryRecord> {
+        if bytes.len() != BINARY_SIZE || &bytes[0..4] != MAGIC {
+            return None;
+        }
+        let mut pos = 4;
+        let mut next = |size: usize| {
+            pos += size;
+            &bytes[pos - size..pos]
+        };
+        let timestamp = f64::from_le_bytes(next(8).try_into().ok()?);
+        let frame_num = u64::from_le_bytes(next(8).try_into().ok()?);
+        let flags = next(1)[0];
+        let dx = i32::from_le_bytes(next(4).try_into().ok()?);
+        let dy = i32::from_le_bytes(next(4).try_into().ok()?);
+        let detection_certainty = f64::from_le_bytes(next(8).try_into().ok()?);
+        let ex = i32::from_le_bytes(next(4).try_into().ok()?);
+        let ey = i32::from_le_bytes(next(4).try_into().ok()?);
+        let mut values = [0.0; 8];
+        for value in values.iter_mut() {
+            *value = f64::from_le_bytes(next(8).try_into().ok()?);
+        }
+        let state = state_from_byte(next(1)[0])?;
+        let battery = f64::from_le_bytes(next(8).try_into().ok()?);
+
+        Some(TelemetryRecord {
+            timestamp,
+            frame_num,
+            detection: if flags & 1 != 0 { Some(GeometricPoint::new(dx, dy)) } else { None },
+            detection_certainty,
+            estimate: if flags & 2 != 0 { Some(GeometricPoint::new(ex, ey)) } else { None },
+            estimated_angle: values[0],
+            estimated_vx: values[1],
+            estimated_vy: values[2],
+            estimation_certainty: values[3],
+            command: (values[4], values[5], values[6], values[7]),
+            state,
+            battery: if flags & 4 != 0 { Some(battery) } else { None },
+        })
+    }
+}
+
+fn state_to_byte(state: FollowerState) -> u8 {
+    match state {
+        FollowerState::Following => 0,
+        FollowerState::Paused => 1,
+        FollowerState::Landed => 2,
+        FollowerState::Stopped => 3,
+    }
+}
+
+fn state_from_byte(byte: u8) -> Option<FollowerState> {
+    match byte {
+        0 => Some(FollowerState::Following),
+        1 => Some(FollowerState::Paused),
+        2 => Some(FollowerState::Landed),
+        3 => Some(FollowerState::Stopped),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::follower_event::FollowerState;
+    use crate::models::geometric_point::GeometricPoint;
+
+    use super::TelemetryRecord;
+
+    fn record() -> TelemetryRecord {
+        TelemetryRecord {
+            timestamp: 1.5,
+            frame_num: 45,
+            detection: Some(GeometricPoint::new(-12, 30)),
+            detection_certainty: 0.8,
+            estimate: Some(GeometricPoint::new(-10, 29)),
+            estimated_angle: 0.3,
+            estimated_vx: 12.5,
+            estimated_vy: -3.0,
+            estimation_certainty: 0.7,
+            command: (0.1, -0.2, 0.0, 0.05),
+            state: FollowerState::Following,
+            battery: None,
+        }
+    }
+
+    #[test]
+    fn binary_format_should_round_trip() {
+        let sut = record();
+
+        assert_eq!(TelemetryRecord::from_bytes(&sut.to_bytes()), Some(sut))
+    }
+
+    #[test]
+    fn invalid_bytes_should_give_none() {
+        assert!(TelemetryRecord::from_bytes(b"HFT1 too short").is_none())
+    }
+
+    #[test]
+    fn json_should_contain_every_field() {
+        let sut = record().to_json();
+
+        assert!(sut.starts_with("{\"timestamp\":1.5,\"frame_num\":45,\"detection\":{\"x\":-12,\"y\":30}"));
+        assert!(sut.ends_with("\"command\":[0.1,-0.2,0,0.05],\"state\":\"Following\",\"battery\":null}"))
+    }
+
+    #[test]
+    fn json_should_contain_null_for_non_finite_values() {
+        let mut sut = record();
+        sut.estimated_vx = f64::NAN;
+        sut.estimated_vy = f64::INFINITY;
+
+        assert!(sut.to_json().contains("\"estimated_vx\":null,\"estimated_vy\":null,"))
+    }
+}
@@ -0,0 +1,227 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use opencv::core::Mat;
+use opencv::highgui::destroy_all_windows;
+
+use crate::{HatFollower, Detection, FollowerEvent, FollowerState, PresentSettings, VideoOutputs, present_frame};
+use crate::traits::{Detector, FrameSource, Controller, Filter};
+
+use crate::models::frame::Frame;
+use crate::models::geometric_point::GeometricPoint;
+
+use crate::utils::frame_registration::FrameRegistration;
+use crate::utils::marker_drawer::MarkerDrawer;
+use crate::utils::text_exporter::TextExporter;
+
+/// Capacity of the channels between the stages of the pipeline.
+const QUEUE_SIZE: usize = 2;
+/// How often the control stage checks for commands while no frames arrive.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A frame with the result of the detection on it, and the markers of the detector.
+struct DetectedFrame {
+    frame: Frame,
+    detection: Detection,
+    markers: MarkerDrawer,
+}
+
+/// A frame that has to be drawn on, saved and shown.
+struct PresentJob {
+    image: Mat,
+    timestamp: f64,
+    markers: MarkerDrawer,
+    settings: PresentSettings,
+}
+
+/// Sends the value if there is room in the channel, otherwise drops it, since the next stage is
+/// busy and it would be stale by the time it got to it. Returns false if the next stage has stopped.
+fn send_or_drop<T>(sender: &SyncSender<T>, value: T) -> bool {
+    !matches!(sender.try_send(value), Err(TrySendError::Disconnected(_)))
+}
+
+/// Skips to the newest value waiting in the channel, dropping the older ones.
+fn skip_to_latest<T>(receiver: &Receiver<T>, value: T) -> T {
+    receiver.try_iter().last().unwrap_or(value)
+}
+
+impl<D: Detector + Send, S: FrameSource + Send, C: Controller + Send, F: Filter> HatFollower<D, S, C, F> {
+    /// Same as run(), but the stages run on separate threads: grabbing the frames, detection,
+    /// filtering and control (on the calling thread), and drawing, saving and showing the video.
+    /// The stages are connected by bounded channels, and a stage that can't keep up skips to the
+    /// newest frame instead of processing stale ones, so a slow frame doesn't delay the next
+    /// command.
+    ///
+    /// Frames from a video file (VideoFileSource) are read at the speed given by their timestamps,
+    /// as if they came from a live camera.
+    pub fn run_pipelined(&mut self) {
+        self.controller().init();
+        self.controller().takeoff();
+        self.landed = false;
+        self.paused = false;
+        self.publish_state();
+        self.validate_settings();
+        self.report_source_warnings();
+
+        let running = AtomicBool::new(true);
+        let compensate_ego_motion = AtomicBool::new(self.settings.compensate_ego_motion);
+        let estimated_position: Mutex<Option<GeometricPoint>> = Mutex::new(None);
+
+        let (frame_sender, frame_receiver) = sync_channel::<Result<Frame, String>>(QUEUE_SIZE);
+        let (detected_sender, detected_receiver) = sync_channel::<Result<DetectedFrame, String>>(QUEUE_SIZE);
+        let (present_sender, present_receiver) = sync_channel::<PresentJob>(QUEUE_SIZE);
+        let (event_sender, event_receiver) = channel::<FollowerEvent>();
+        let mut text_exporter = TextExporter::with_queue_options(self.settings.export_queue_options);
+
+        thread::scope(|scope| {
+            // Grabbing the frames
+            let source = self.source.clone();
+            let running = &running;
+            scope.spawn(move || {
+                let start = Instant::now();
+                let mut first_timestamp = None;
+                while running.load(Ordering::SeqCst) {
+                    let mut frame = Frame::new();
+                    let next_frame = source.lock().unwrap().get_next_frame(&mut frame);
+                    match next_frame {
+                        Ok(true) => {
+                            let since_first = frame.timestamp - *first_timestamp.get_or_insert(frame.timestamp);
+                            let wait = since_first - start.elapsed().as_secs_f64();
+                            if wait > 0.0 {
+                                thread::sleep(Duration::from_secs_f64(wait));
+                            }
+                            if !send_or_drop(&frame_sender, Ok(frame)) {
+                                break;
+                            }
+                        }
+                        Ok(false) => {
+                            break;
+                        }
+                        Err(e) => {
+                            let _ = frame_sender.send(Err(format!("Couldn't get the next frame: {}", e)));
+                            break;
+                        }
+                    }
+                }
+            });
+
+            // Detection
+            let detector = self.detector.clone();
+            let p_c = self.p_c.clone();
+            let compensate_ego_motion = &compensate_ego_motion;
+            let estimated_position = &estimated_position;
+            scope.spawn(move || {
+                let mut frame_registration = FrameRegistration::new();
+                while let Ok(result) = frame_receiver.recv() {
+                    let frame = match skip_to_latest(&frame_receiver, result) {
+                        Ok(frame) => frame,
+                        Err(e) => {
+                            let _ = detected_sender.send(Err(e));
+                            break;
+                        }
+                    };
+
+                    let mut detector = detector.lock().unwrap();
+                    let frame_registration = if compensate_ego_motion.load(Ordering::SeqCst) {
+                        Some(&mut frame_registration)
+                    } else {
+                        None
+                    };
+                    let point_for_detector = estimated_position.lock().unwrap().clone();
+                    let detection = Self::detect(&mut detector, frame_registration, &frame.image, point_for_detector, &p_c);
+                    let mut markers = MarkerDrawer::new();
+                    detector.draw_on_image(&mut markers);
+                    drop(detector);
+
+                    if !send_or_drop(&detected_sender, Ok(DetectedFrame { frame, detection, markers })) {
+                        break;
+                    }
+                }
+            });
+
+            // Drawing, saving and showing the video
+            let p_c = self.p_c.clone();
+            scope.spawn(move || {
+                let mut video_outputs = VideoOutputs::new();
+                while let Ok(job) = present_receiver.recv() {
+                    let mut job = skip_to_latest(&present_receiver, job);
+                    let events = present_frame(&mut job.image, job.timestamp, job.markers, &p_c, &job.settings, None, &mut video_outputs);
+                    for event in events {
+                        let _ = event_sender.send(event);
+                    }
+                }
+                for e in video_outputs.finish() {
+                    let _ = event_sender.send(FollowerEvent::Error(e));
+                }
+                destroy_all_windows().unwrap();
+            });
+
+            // Filtering and control
+            loop {
+                if self.handle_commands() {
+                    break;
+                }
+                compensate_ego_motion.store(self.settings.compensate_ego_motion, Ordering::SeqCst);
+                // Runs while no frames arrive too, so the controller can notice the lost video.
+                self.controller().tick();
+
+                let result = match detected_receiver.recv_timeout(COMMAND_POLL_INTERVAL) {
+                    Ok(result) => skip_to_latest(&detected_receiver, result),
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let detected = match result {
+                    Ok(detected) => detected,
+                    Err(e) => {
+                        self.publish(FollowerEvent::Error(e));
+                        break;
+                    }
+                };
+
+                self.controller().frame_received(&detected.frame);
+                let output = self.process_detection(detected.frame.timestamp, detected.detection, None);
+                self.record_frame(&detected.frame, &output);
+                self.export_state(detected.frame.timestamp, &output);
+                *estimated_position.lock().unwrap() = output.estimated_position.clone();
+                if let Some(command) = output.command {
+                    self.control_the_drone(command, &mut text_exporter);
+                }
+                self.report_text_exporter(&text_exporter);
+
+                let settings = self.present_settings();
+                if settings.is_needed() {
+                    let mut markers = if self.settings.draw_detection { detected.markers } else { MarkerDrawer::new() };
+                    self.draw_markers(&mut markers);
+                    send_or_drop(&present_sender, PresentJob {
+                        image: detected.frame.image,
+                        timestamp: detected.frame.timestamp,
+                        markers,
+                        settings,
+                    });
+                }
+                for event in event_receiver.try_iter() {
+                    self.publish(event);
+                }
+            }
+
+            // Lets the other stages finish.
+            running.store(false, Ordering::SeqCst);
+            drop(detected_receiver);
+            drop(present_sender);
+        });
+
+        if !self.landed {
+            self.controller().land();
+        }
+        self.controller().shutdown();
+        // The errors of the video outputs sent after the last frame.
+        for event in event_receiver.try_iter() {
+            self.publish(event);
+        }
+        self.finish_exports(text_exporter);
+        self.publish(FollowerEvent::StateChanged(FollowerState::Stopped));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::models::geometric_point::GeometricPoint;
+
+/// The result of processing a single frame with HatFollower::step. Positions are in the descartes
+/// coordinate system of the image.
+#[derive(Clone)]
+pub struct StepOutput {
+    /// The number of the frame, counted from 1.
+    pub frame_num: usize,
+    /// Time since the previous frame in seconds (0.0 for the first one).
+    pub dt: f64,
+    pub detected_position: Option<GeometricPoint>,
+    pub detected_angle: Option<f64>,
+    pub detection_certainty: f64,
+    pub estimated_position: Option<GeometricPoint>,
+    pub estimated_angle: f64,
+    /// Estimated velocity of the hat in pixels/second.
+    pub estimated_vx: f64,
+    pub estimated_vy: f64,
+    pub estimation_certainty: f64,
+    /// The command (left_right, back_front, down_up, turn_left_right) that should be sent to the
+    /// drone, or None if no new command is needed.
+    pub command: Option<(f64, f64, f64, f64)>,
+}
@@ -1,6 +1,10 @@
 use opencv::core::*;
 
+use crate::models::frame::Frame;
 use crate::models::geometric_point::GeometricPoint;
+use crate::models::hat::Hat;
+use crate::models::rigid_motion::RigidMotion;
+use crate::models::telemetry::Telemetry;
 
 use crate::utils::point_converter::PointConverter;
 use crate::utils::marker_drawer::MarkerDrawer;
@@ -20,11 +24,27 @@ pub trait Detector {
 
     /// Should display visually some parts of the detection. (optional)
     fn draw_on_image(&self, m_d: &mut MarkerDrawer);
+
+    /// Should start looking for the given hat instead of the previous one. (optional)
+    fn change_hat(&mut self, _hat: Hat) {
+    }
+
+    /// Should follow the object closest to the given point (in the descartes coordinate system) if
+    /// there is more than one. (optional)
+    fn switch_target(&mut self, _target: GeometricPoint) {
+    }
 }
 
 pub trait Filter {
-    /// Updates the estimation based on new information.
-    fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, cert: f64);
+    /// Updates the estimation based on new information. dt is the time in seconds since the
+    /// previous update (0.0 for the first one).
+    fn update_estimation(&mut self, point: Option<GeometricPoint>, angle: Option<f64>, cert: f64, dt: f64);
+
+    /// Receives the motion of the whole image since the last frame (caused by the movement of the
+    /// drone), before the next update_estimation. Filters can subtract it from the movement of the
+    /// hat to get its velocity relative to the ground. (optional)
+    fn update_camera_motion(&mut self, _motion: &RigidMotion) {
+    }
 
     /// Returns the estimated position of the hat.
     fn get_estimated_position(&self) -> Option<GeometricPoint>;
@@ -32,17 +52,62 @@ pub trait Filter {
     /// Returns the estimated angle of the hat.
     fn get_estimated_angle(&self) -> f64;
 
-    /// Returns the estimated horizontal speed of the hat.
+    /// Returns the estimated horizontal speed of the hat in pixels/s.
     fn get_estimated_vx(&self) -> f64;
 
-    /// Returns the estimated vertical speed of the hat.
+    /// Returns the estimated vertical speed of the hat in pixels/s.
     fn get_estimated_vy(&self) -> f64;
 
     /// Returns the certainty of the estimation.
     fn get_estimation_certainty(&self) -> f64;
 
+    /// Returns the predicted position of the hat `time` seconds after the last estimation, using
+    /// the motion model of the filter. By default the estimated position is moved with the estimated
+    /// velocity.
+    fn predict_position(&self, time: f64) -> Option<GeometricPoint> {
+        self.get_estimated_position().map(|p| GeometricPoint::new(
+            p.x + (self.get_estimated_vx() * time).round() as i32,
+            p.y + (self.get_estimated_vy() * time).round() as i32
+        ))
+    }
+
     /// Returns the certainty of the estimation.
     fn draw_on_image(&self, m_d: &mut MarkerDrawer);
+
+    /// Forgets everything the filter learned, as if no frame was seen yet. It is called when the
+    /// playback jumps to another frame. (optional)
+    fn reset(&mut self) {
+    }
+}
+
+pub trait FrameSource {
+    /// Should return the video's height in pixels
+    fn get_video_height(&self) -> usize;
+
+    /// Should return the video's width in pixels
+    fn get_video_width(&self) -> usize;
+
+    /// Should return the next image, along with the time it was captured at. Returns false if
+    /// there are no more frames.
+    fn get_next_frame(&mut self, frame: &mut Frame) -> opencv::Result<bool>;
+
+    /// Should return the number of the frame that will be given out next (counted from 0), if the
+    /// source knows it.
+    fn get_position(&self) -> Option<usize> {
+        None
+    }
+
+    /// Should make the source continue from the given frame. Returns false if the source can't
+    /// seek (for example a live camera).
+    fn seek(&mut self, _frame_num: usize) -> opencv::Result<bool> {
+        Ok(false)
+    }
+
+    /// Should return the problems that don't stop the source (for example a camera that doesn't
+    /// use the requested resolution). The HatFollower publishes them as errors when it starts.
+    fn get_warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 pub trait Controller {
@@ -62,15 +127,6 @@ pub trait Controller {
     /// Should halt all movement
     fn stop(&mut self);
 
-    /// Should return the video's height in pixels
-    fn get_video_height(&self) -> usize;
-
-    /// Should return the video's width in pixels
-    fn get_video_width(&self) -> usize;
-
-    /// Should return current image from drone
-    fn get_next_frame(&mut self, img: &mut Mat) -> opencv::Result<bool>;
-
     /// Conversion rate between pixels/dt and drone speed which is in (-1.0, 1.0), where dt is the
     /// time difference between frames
     fn get_kv(&self) -> f64;
@@ -79,4 +135,44 @@ pub trait Controller {
     /// time difference between frames, and da is the angle difference between frames.
     fn get_ka(&self) -> f64;
 
+    /// Called by the HatFollower after every frame received from the FrameSource. (optional)
+    fn frame_received(&mut self, _frame: &Frame) {
+    }
+
+    /// Called by the HatFollower on every iteration of its loop, even when no frame arrived (the
+    /// source stalled or failed), so the controller can do its periodic work (for example
+    /// checking the state of the drone, or landing it when the video is lost). (optional)
+    fn tick(&mut self) {
+    }
+
+    /// Should return the latest state reported by the drone. (optional)
+    fn get_telemetry(&self) -> Telemetry {
+        Telemetry::new()
+    }
+
+    /// Should return the speed of the drone in m/s when it is given a move command of 1.0. (optional)
+    fn get_max_speed(&self) -> f64 {
+        1.0
+    }
+
+    /// Should return the turn rate of the drone in rad/s when it is given a turn command of 1.0.
+    /// (optional)
+    fn get_max_turn_rate(&self) -> f64 {
+        1.0
+    }
+
+    /// Converts velocities in physical units (m/s for left_right, back_front, down_up and rad/s for
+    /// turn_left_right) to the command range of move_all. By default it divides them with the
+    /// maximum speed and turn rate of the drone.
+    fn convert_metric(&self, left_right: f64, back_front: f64, down_up: f64, turn_left_right: f64) -> (f64, f64, f64, f64) {
+        let max_speed = self.get_max_speed();
+        let max_turn_rate = self.get_max_turn_rate();
+        (
+            (left_right / max_speed).min(1.0).max(-1.0),
+            (back_front / max_speed).min(1.0).max(-1.0),
+            (down_up / max_speed).min(1.0).max(-1.0),
+            (turn_left_right / max_turn_rate).min(1.0).max(-1.0)
+        )
+    }
+
 }
@@ -0,0 +1,91 @@
+use crate::models::geometric_point::GeometricPoint;
+use crate::models::telemetry::Telemetry;
+
+/// The bottom camera is rigidly mounted on the drone, so every time the drone tilts (for example
+/// to start moving) the hat shifts on the image, even if neither of them moved. This struct
+/// re-projects points from the tilted camera to a virtual camera that is level and points straight
+/// down.
+///
+/// The image is expected to have its top towards the front of the drone, and its right towards the
+/// right side of the drone.
+pub struct AttitudeCompensator {
+    focal_length: f64,
+}
+
+impl AttitudeCompensator {
+    /// Requires the focal length of the camera in pixels.
+    ///
+    /// Usage:
+    ///
+    /// ```
+    /// use rust_drone_follow::utils::attitude_compensator::AttitudeCompensator;
+    /// // ...
+    /// # fn main() {
+    ///     let compensator = AttitudeCompensator::new(500.0);
+    /// # }
+    /// ```
+    pub fn new(focal_length: f64) -> AttitudeCompensator {
+        AttitudeCompensator {
+            focal_length,
+        }
+    }
+
+    /// Returns where the given point (in the descartes coordinate-system of PointConverter) would be
+    /// on the image of the level camera. Missing angles are considered to be 0. Points that would be
+    /// above the horizon are returned unchanged.
+    pub fn compensate(&self, point: &GeometricPoint, telemetry: &Telemetry) -> GeometricPoint {
+        let f = self.focal_length;
+        let (sin_r, cos_r) = telemetry.roll.unwrap_or(0.0).sin_cos();
+        let (sin_p, cos_p) = telemetry.pitch.unwrap_or(0.0).sin_cos();
+        let (x, y) = (point.x as f64, point.y as f64);
+
+        // The ray of the point in the coordinate-system of the camera (right, front, down) is
+        // (x, y, f), which is first rotated by the roll and then by the pitch of the drone.
+        let (x1, y1, z1) = (x * cos_r - f * sin_r, y, x * sin_r + f * cos_r);
+        let (x2, y2, z2) = (x1, y1 * cos_p + z1 * sin_p, z1 * cos_p - y1 * sin_p);
+
+        if z2 <= 0.0 {
+            return point.clone();
+        }
+        GeometricPoint::new((f * x2 / z2).round() as i32, (f * y2 / z2).round() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::geometric_point::GeometricPoint;
+    use crate::models::telemetry::Telemetry;
+
+    use super::AttitudeCompensator;
+
+    fn telemetry(pitch: f64, roll: f64) -> Telemetry {
+        let mut telemetry = Telemetry::new();
+        telemetry.pitch = Some(pitch);
+        telemetry.roll = Some(roll);
+        telemetry
+    }
+
+    #[test]
+    fn level_drone_should_not_change_point() {
+        let sut = AttitudeCompensator::new(500.0);
+        let point = sut.compensate(&GeometricPoint::new(120, -40), &Telemetry::new());
+
+        assert!(point.x == 120 && point.y == -40)
+    }
+
+    #[test]
+    fn nose_up_should_move_center_forward() {
+        let sut = AttitudeCompensator::new(500.0);
+        let point = sut.compensate(&GeometricPoint::new(0, 0), &telemetry(0.1, 0.0));
+
+        assert!(point.x == 0 && point.y == (500.0 * 0.1_f64.tan()).round() as i32)
+    }
+
+    #[test]
+    fn right_side_down_should_move_center_left() {
+        let sut = AttitudeCompensator::new(500.0);
+        let point = sut.compensate(&GeometricPoint::new(0, 0), &telemetry(0.0, 0.1));
+
+        assert!(point.x == -(500.0 * 0.1_f64.tan()).round() as i32 && point.y == 0)
+    }
+}
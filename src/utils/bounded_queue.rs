@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What happens when an item is sent to a full queue.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QueuePolicy {
+    /// The sender waits until there is room in the queue.
+    Block,
+    /// The oldest item in the queue is dropped to make room for the new one.
+    DropOldest,
+    /// The new item is dropped.
+    DropNewest,
+}
+
+/// Options of the queue between a thread and the one writing its output to the disk.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct QueueOptions {
+    /// The most items that can wait in the queue.
+    pub capacity: usize,
+    pub policy: QueuePolicy,
+    /// How long the items left in the queue are waited for to be written when the writer is
+    /// dropped.
+    pub flush_timeout: Duration,
+}
+
+impl QueueOptions {
+    /// 64 items, blocking when full, with 5 seconds to flush.
+    pub fn new() -> QueueOptions {
+        QueueOptions {
+            capacity: 64,
+            policy: QueuePolicy::Block,
+            flush_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    closed: bool,
+    dropped: usize,
+}
+
+struct Shared<T> {
+    state: Mutex<QueueState<T>>,
+    changed: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+}
+
+/// The sending half of a bounded queue, the queue is closed when it is dropped.
+pub struct BoundedSender<T> {
+    shared: Arc<Shared<T>>,
+    capacity: usize,
+    policy: QueuePolicy,
+}
+
+/// The receiving half of a bounded queue, the queue is closed when it is dropped (for example if
+/// the receiving thread panics), so the sender never waits for it forever.
+pub struct BoundedReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Creates a queue with the given capacity (at least 1) and policy.
+pub fn bounded_queue<T>(options: &QueueOptions) -> (BoundedSender<T>, BoundedReceiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(QueueState {
+            items: VecDeque::new(),
+            closed: false,
+            dropped: 0,
+        }),
+        changed: Condvar::new(),
+    });
+    (
+        BoundedSender { shared: Arc::clone(&shared), capacity: options.capacity.max(1), policy: options.policy },
+        BoundedReceiver { shared },
+    )
+}
+
+impl<T> BoundedSender<T> {
+    /// Puts the item into the queue. Returns false if an item was dropped because the queue was
+    /// full, or the queue was closed.
+    pub fn send(&self, item: T) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        if self.policy == QueuePolicy::Block {
+            while state.items.len() >= self.capacity && !state.closed {
+                state = self.shared.changed.wait(state).unwrap();
+            }
+        }
+        if state.closed {
+            state.dropped += 1;
+            return false;
+        }
+
+        let mut queued = true;
+        if state.items.len() >= self.capacity {
+            state.dropped += 1;
+            queued = false;
+            if self.policy == QueuePolicy::DropNewest {
+                return false;
+            }
+            state.items.pop_front();
+        }
+        state.items.push_back(item);
+        self.shared.changed.notify_all();
+        queued
+    }
+
+    /// Returns the number of items dropped so far.
+    pub fn get_dropped(&self) -> usize {
+        self.shared.state.lock().unwrap().dropped
+    }
+
+    /// Closes the queue: the receiver gets the items left in it, and then None.
+    pub fn close(&self) {
+        self.shared.close();
+    }
+}
+
+impl<T> Drop for BoundedSender<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<T> BoundedReceiver<T> {
+    /// Waits for the next item. Returns None if the queue is closed and empty.
+    pub fn recv(&self) -> Option<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.shared.changed.notify_all();
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.shared.changed.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T> Drop for BoundedReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Waits for the thread to finish, at most for the given time. Returns None if it didn't finish
+/// in time, in which case it is left running in the background, otherwise the result of the
+/// thread (an error if it panicked).
+pub fn join_with_timeout(join_handle: thread::JoinHandle<()>, timeout: Duration) -> Option<thread::Result<()>> {
+    let deadline = Instant::now() + timeout;
+    while !join_handle.is_finished() {
+        if Instant::now() >= deadline {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    Some(join_handle.join())
+}
+
+/// Closes the queue and waits for its writer thread to finish, at most for the flush_timeout of
+/// the options. Returns an error (starting with the name of the output) if the thread didn't
+/// finish in time or panicked, the output may be incomplete then. Does nothing if the thread has
+/// already been waited for.
+pub fn finish_writer<T>(sender: &BoundedSender<T>, join_handle: &mut Option<thread::JoinHandle<()>>, options: &QueueOptions, name: &str) -> Option<String> {
+    sender.close();
+    match join_with_timeout(join_handle.take()?, options.flush_timeout) {
+        None => Some(format!("{}: the queue couldn't be written in time, the output may be incomplete", name)),
+        Some(Err(_)) => Some(format!("{}: the writer thread failed, the output may be incomplete", name)),
+        Some(Ok(())) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{bounded_queue, finish_writer, join_with_timeout, QueueOptions, QueuePolicy};
+
+    fn options(policy: QueuePolicy) -> QueueOptions {
+        let mut options = QueueOptions::new();
+        options.capacity = 2;
+        options.policy = policy;
+        options
+    }
+
+    #[test]
+    fn drop_oldest_should_keep_the_newest_items() {
+        let (sut, receiver) = bounded_queue(&options(QueuePolicy::DropOldest));
+        assert!(sut.send(1) && sut.send(2));
+        assert!(!sut.send(3));
+        sut.close();
+
+        assert_eq!(sut.get_dropped(), 1);
+        assert_eq!((receiver.recv(), receiver.recv(), receiver.recv()), (Some(2), Some(3), None))
+    }
+
+    #[test]
+    fn drop_newest_should_keep_the_oldest_items() {
+        let (sut, receiver) = bounded_queue(&options(QueuePolicy::DropNewest));
+        sut.send(1);
+        sut.send(2);
+        sut.send(3);
+        drop(sut);
+
+        assert_eq!((receiver.recv(), receiver.recv(), receiver.recv()), (Some(1), Some(2), None))
+    }
+
+    #[test]
+    fn block_should_wait_for_the_receiver() {
+        let (sut, receiver) = bounded_queue(&options(QueuePolicy::Block));
+        let join_handle = thread::spawn(move || {
+            let mut received = Vec::new();
+            while let Some(item) = receiver.recv() {
+                thread::sleep(Duration::from_millis(1));
+                received.push(item);
+            }
+            assert_eq!(received, (0..10).collect::<Vec<i32>>());
+        });
+        for i in 0..10 {
+            assert!(sut.send(i));
+        }
+        drop(sut);
+
+        assert!(matches!(join_with_timeout(join_handle, Duration::from_secs(5)), Some(Ok(()))))
+    }
+
+    #[test]
+    fn panicked_thread_should_be_reported() {
+        let join_handle = thread::spawn(|| panic!("writer failed"));
+
+        assert!(matches!(join_with_timeout(join_handle, Duration::from_secs(5)), Some(Err(_))))
+    }
+
+    #[test]
+    fn sending_to_a_dropped_receiver_should_not_block() {
+        let (sut, receiver) = bounded_queue(&options(QueuePolicy::Block));
+        drop(receiver);
+
+        assert!(!sut.send(1) && !sut.send(2) && !sut.send(3));
+        assert_eq!(sut.get_dropped(), 3)
+    }
+
+    #[test]
+    fn finished_writer_should_report_a_panic_once() {
+        let (sut, receiver) = bounded_queue::<i32>(&options(QueuePolicy::Block));
+        let mut join_handle = Some(thread::spawn(move || {
+            receiver.recv();
+            panic!("writer failed")
+        }));
+
+        assert!(finish_writer(&sut, &mut join_handle, &QueueOptions::new(), "test").unwrap().starts_with("test: "));
+        assert!(finish_writer(&sut, &mut join_handle, &QueueOptions::new(), "test").is_none())
+    }
+}
@@ -0,0 +1,97 @@
+use opencv::core::*;
+use opencv::calib3d::{calibrate_camera, find_chessboard_corners, CALIB_CB_ADAPTIVE_THRESH, CALIB_CB_NORMALIZE_IMAGE, CALIB_CB_FAST_CHECK};
+use opencv::imgproc::corner_sub_pix;
+use opencv::types::{VectorOfMat, VectorOfPoint2f, VectorOfPoint3f, VectorOfVectorOfPoint2f, VectorOfVectorOfPoint3f};
+use opencv::videoio::{VideoCapture, VideoCaptureTrait, CAP_ANY};
+
+use crate::models::camera_model::CameraModel;
+
+use crate::utils::opencv_custom::to_grayscale;
+
+/// Under this number of frames with a found chessboard the calibration is refused.
+const MIN_BOARD_VIEWS: usize = 5;
+
+/// Calculates the CameraModel of a camera from a video recorded with it, in which a chessboard is
+/// shown from different angles.
+///
+/// board_size is the number of inner corners of the chessboard (columns, rows), square_size is the
+/// length of the side of a square (the unit doesn't matter for the intrinsics). Only every
+/// frame_step-th frame of the video is used, since consecutive frames are almost the same.
+///
+/// Usage:
+/// ```no_run
+/// use rust_drone_follow::utils::camera_calibration::calibrate_from_video;
+/// use rust_drone_follow::utils::camera_model_file::write_file;
+/// // ...
+/// # fn main() {
+///     let camera_model = calibrate_from_video("chessboard.mp4", (9, 6), 0.025, 15).unwrap();
+///     write_file("camera.calib", &camera_model);
+/// # }
+/// ```
+pub fn calibrate_from_video(filename: &str, board_size: (i32, i32), square_size: f64, frame_step: usize) -> opencv::Result<CameraModel> {
+    let mut video = VideoCapture::from_file(filename, CAP_ANY)?;
+    if !video.is_opened()? {
+        return Err(opencv::Error::new(StsObjectNotFound, format!("Could not open video file: {}", filename)));
+    }
+
+    let pattern_size = Size::new(board_size.0, board_size.1);
+    let mut object_points = VectorOfVectorOfPoint3f::new();
+    let mut image_points = VectorOfVectorOfPoint2f::new();
+    let mut image_size = Size::new(0, 0);
+
+    let mut img = Mat::default()?;
+    let mut frame_num = 0;
+    while video.read(&mut img)? {
+        frame_num += 1;
+        if (frame_num - 1) % frame_step.max(1) != 0 {
+            continue;
+        }
+        let gray = to_grayscale(&img);
+        image_size = gray.size()?;
+
+        let mut corners = VectorOfPoint2f::new();
+        let flags = CALIB_CB_ADAPTIVE_THRESH + CALIB_CB_NORMALIZE_IMAGE + CALIB_CB_FAST_CHECK;
+        if find_chessboard_corners(&gray, pattern_size, &mut corners, flags)? {
+            corner_sub_pix(&gray, &mut corners, Size::new(11, 11), Size::new(-1, -1),
+                           TermCriteria::new(TermCriteria_Type::COUNT as i32 + TermCriteria_Type::EPS as i32, 30, 0.001)?)?;
+            image_points.push(corners);
+            object_points.push(board_points(board_size, square_size));
+        }
+    }
+
+    if image_points.len() < MIN_BOARD_VIEWS {
+        return Err(opencv::Error::new(StsError, format!(
+            "The chessboard was only found on {} frames, at least {} are needed", image_points.len(), MIN_BOARD_VIEWS
+        )));
+    }
+
+    let mut camera_matrix = Mat::default()?;
+    let mut dist_coeffs = Mat::default()?;
+    let mut rvecs = VectorOfMat::new();
+    let mut tvecs = VectorOfMat::new();
+    calibrate_camera(&object_points, &image_points, image_size, &mut camera_matrix, &mut dist_coeffs,
+                     &mut rvecs, &mut tvecs, 0,
+                     TermCriteria::new(TermCriteria_Type::COUNT as i32 + TermCriteria_Type::EPS as i32, 30, f64::EPSILON)?)?;
+
+    let coeff = |i: i32| -> opencv::Result<f64> {
+        if i < dist_coeffs.total()? as i32 { Ok(*dist_coeffs.at::<f64>(i)?) } else { Ok(0.0) }
+    };
+    Ok(CameraModel::new(
+        *camera_matrix.at_2d::<f64>(0, 0)?,
+        *camera_matrix.at_2d::<f64>(1, 1)?,
+        *camera_matrix.at_2d::<f64>(0, 2)?,
+        *camera_matrix.at_2d::<f64>(1, 2)?,
+        [coeff(0)?, coeff(1)?, coeff(2)?, coeff(3)?, coeff(4)?]
+    ))
+}
+
+/// Returns the corners of the chessboard in its own coordinate system (on the z = 0 plane).
+fn board_points(board_size: (i32, i32), square_size: f64) -> VectorOfPoint3f {
+    let mut points = VectorOfPoint3f::new();
+    for row in 0..board_size.1 {
+        for col in 0..board_size.0 {
+            points.push(Point3f::new((col as f64 * square_size) as f32, (row as f64 * square_size) as f32, 0.0));
+        }
+    }
+    points
+}
@@ -0,0 +1,45 @@
+use std::fs;
+
+use crate::models::camera_model::CameraModel;
+
+macro_rules! parse_input {
+    ($x:expr, $t:ident) => ($x.trim().parse::<$t>().unwrap())
+}
+
+/// Reads a calibration file which is in the following format:
+///
+/// fx fy cx cy
+///
+/// k1 k2 p1 p2 k3
+///
+/// Where fx, fy are the focal lengths and cx, cy is the principal point of the camera in pixels,
+/// and k1, k2, p1, p2, k3 are the distortion coefficients of the lens (as used by OpenCV).
+///
+/// Any other rows after this will not be read.
+pub fn read_file(filename: &str) -> CameraModel {
+    let contents = fs::read_to_string(filename)
+        .expect("Something went wrong reading the file");
+    let rows: Vec<&str> = contents.split('\n').collect::<Vec<&str>>();
+    let intrinsics = rows[0].split_whitespace()
+        .map(|s| parse_input!(s, f64)).collect::<Vec<f64>>();
+    let distortion = rows[1].split_whitespace()
+        .map(|s| parse_input!(s, f64)).collect::<Vec<f64>>();
+
+    CameraModel::new(
+        intrinsics[0],
+        intrinsics[1],
+        intrinsics[2],
+        intrinsics[3],
+        [distortion[0], distortion[1], distortion[2], distortion[3], distortion[4]]
+    )
+}
+
+/// Writes the camera model into a file in the format that read_file can read.
+pub fn write_file(filename: &str, camera_model: &CameraModel) {
+    let [k1, k2, p1, p2, k3] = camera_model.distortion;
+    let contents = format!("{} {} {} {}\n{} {} {} {} {}\n",
+                           camera_model.fx, camera_model.fy, camera_model.cx, camera_model.cy,
+                           k1, k2, p1, p2, k3);
+    fs::write(filename, contents)
+        .expect("Something went wrong writing the file");
+}
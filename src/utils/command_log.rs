@@ -0,0 +1,109 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+/// A command read from the file written by the HatFollower when save_commands is set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedCommand {
+    pub frame_num: usize,
+    pub left_right: f64,
+    pub back_front: f64,
+    pub down_up: f64,
+    pub turn_left_right: f64,
+    /// Capture time of the frame in seconds. Older logs don't contain it.
+    pub timestamp: Option<f64>,
+}
+
+impl LoggedCommand {
+    /// Reads a row in the following format (the timestamp is optional):
+    ///
+    /// frame_num left_right back_front down_up turn_left_right timestamp
+    ///
+    /// Returns None if the row is not valid, or a value in it isn't finite (for example "inf").
+    pub fn from_line(line: &str) -> Option<LoggedCommand> {
+        let values = line.split_whitespace().collect::<Vec<&str>>();
+        if values.len() != 5 && values.len() != 6 {
+            return None;
+        }
+        let number = |i: usize| values[i].parse::<f64>().ok().filter(|value| value.is_finite());
+        Some(LoggedCommand {
+            frame_num: values[0].parse::<usize>().ok()?,
+            left_right: number(1)?,
+            back_front: number(2)?,
+            down_up: number(3)?,
+            turn_left_right: number(4)?,
+            timestamp: match values.get(5) {
+                Some(_) => Some(number(5)?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// Parses the contents of a command log, empty rows are skipped. Returns an error for an invalid
+/// row, and for a timestamp that isn't later than the one before it.
+pub fn parse_command_log(text: &str) -> std::io::Result<Vec<LoggedCommand>> {
+    let mut commands: Vec<LoggedCommand> = Vec::new();
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let command = LoggedCommand::from_line(line)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Invalid command in row {}: {}", i + 1, line)))?;
+        if let (Some(previous), Some(timestamp)) = (commands.last().and_then(|c| c.timestamp), command.timestamp) {
+            if timestamp <= previous {
+                return Err(Error::new(ErrorKind::InvalidData, format!("The timestamp in row {} isn't later than the one before it: {}", i + 1, line)));
+            }
+        }
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+/// Reads the file written by the HatFollower when save_commands is set.
+pub fn read_command_log(filename: &str) -> std::io::Result<Vec<LoggedCommand>> {
+    parse_command_log(&fs::read_to_string(filename)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoggedCommand, parse_command_log};
+
+    #[test]
+    fn row_without_timestamp_should_be_read() {
+        let sut = LoggedCommand::from_line("12 0.1 -0.2 0 0.5").unwrap();
+
+        assert_eq!(sut, LoggedCommand {
+            frame_num: 12,
+            left_right: 0.1,
+            back_front: -0.2,
+            down_up: 0.0,
+            turn_left_right: 0.5,
+            timestamp: None,
+        })
+    }
+
+    #[test]
+    fn row_with_timestamp_should_be_read() {
+        let sut = LoggedCommand::from_line("12 0.1 -0.2 0 0.5 0.4").unwrap();
+
+        assert_eq!(sut.timestamp, Some(0.4))
+    }
+
+    #[test]
+    fn invalid_row_should_give_an_error_with_its_number() {
+        let sut = parse_command_log("1 0 0 0 0\n\n3 0 zero 0 0\n");
+
+        assert!(sut.unwrap_err().to_string().contains("row 3"))
+    }
+
+    #[test]
+    fn infinite_timestamp_should_be_rejected() {
+        let sut = parse_command_log("1 0 0 0 0 0.1\n2 0 0 0 0 inf\n");
+
+        assert!(sut.unwrap_err().to_string().contains("row 2"))
+    }
+
+    #[test]
+    fn timestamp_going_back_should_be_rejected() {
+        let sut = parse_command_log("1 0 0 0 0 0.2\n2 0 0 0 0 0.3\n3 0 0 0 0 0.3\n");
+
+        assert!(sut.unwrap_err().to_string().contains("row 3"))
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::traits::Controller;
+
+use crate::utils::command_log::LoggedCommand;
+
+/// The longest time the replay sleeps without checking the stop flag.
+const MAX_SLEEP: Duration = Duration::from_millis(50);
+/// The lowest speed and frame rate used, smaller (zero, negative or NaN) values are raised to these.
+const MIN_SPEED: f64 = 0.01;
+const MIN_FRAME_RATE: f64 = 1.0;
+
+/// Re-issues the commands of a command log (see read_command_log) to a controller with their
+/// original timing, so a manoeuvre can be reproduced on the real drone or in a simulator.
+///
+/// The timing comes from the timestamps of the log, or if the log doesn't contain them, from the
+/// frame numbers and the frame_rate. The controller has to be initialized and in the air already,
+/// the replayer only moves it, and stops it at the end.
+pub struct CommandReplayer {
+    commands: Vec<LoggedCommand>,
+    /// Frames per second of the video the log was made on, only used if the log has no timestamps.
+    /// Values under 1 are replaced by 1.
+    pub frame_rate: f64,
+    /// Multiplies the speed of the replay (2.0 replays the commands in half of the original time).
+    /// Values under 0.01 (including zero and negative values) are replaced by 0.01.
+    pub speed: f64,
+    stop: Arc<AtomicBool>,
+}
+
+impl CommandReplayer {
+    /// Usage:
+    /// ```
+    /// use rust_drone_follow::utils::command_log::read_command_log;
+    /// use rust_drone_follow::utils::command_replayer::CommandReplayer;
+    /// // ...
+    /// # fn main() {
+    ///     let replayer = CommandReplayer::new(read_command_log("commands.txt").unwrap());
+    /// # }
+    /// ```
+    pub fn new(commands: Vec<LoggedCommand>) -> CommandReplayer {
+        CommandReplayer {
+            commands,
+            frame_rate: 30.0,
+            speed: 1.0,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns a flag that stops the replay (from any thread) if it is set to true.
+    pub fn get_stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Returns the time of every command in seconds, relative to the first one (after applying
+    /// the speed).
+    pub fn get_schedule(&self) -> Vec<f64> {
+        let first = match self.commands.first() {
+            Some(first) => first,
+            None => return Vec::new(),
+        };
+        let has_timestamps = self.commands.iter().all(|c| c.timestamp.is_some());
+        // max also replaces NaN.
+        let speed = self.speed.max(MIN_SPEED);
+        let frame_rate = self.frame_rate.max(MIN_FRAME_RATE);
+        self.commands.iter()
+            .map(|c| {
+                let time = if has_timestamps {
+                    c.timestamp.unwrap() - first.timestamp.unwrap()
+                } else {
+                    (c.frame_num as f64 - first.frame_num as f64) / frame_rate
+                };
+                time / speed
+            })
+            .collect()
+    }
+
+    /// Sends the commands to the controller, blocking until the last one was sent or the replay
+    /// was stopped, and stops the drone at the end. Returns the number of commands sent.
+    pub fn replay<C: Controller>(&self, controller: &mut C) -> usize {
+        let start = Instant::now();
+        let mut sent = 0;
+        for (command, time) in self.commands.iter().zip(self.get_schedule()) {
+            let target = start + Duration::from_secs_f64(time.max(0.0));
+            loop {
+                if self.stop.load(Ordering::SeqCst) {
+                    controller.stop();
+                    return sent;
+                }
+                let now = Instant::now();
+                if now >= target {
+                    break;
+                }
+                thread::sleep((target - now).min(MAX_SLEEP));
+            }
+            controller.move_all(command.left_right, command.back_front, command.down_up, command.turn_left_right);
+            sent += 1;
+        }
+        controller.stop();
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use crate::controllers::recording_controller::RecordingController;
+
+    use crate::utils::command_log::parse_command_log;
+
+    use super::CommandReplayer;
+
+    #[test]
+    fn schedule_should_use_frame_rate_without_timestamps() {
+        let mut sut = CommandReplayer::new(parse_command_log("10 0 0 0 0\n25 0 0 0 0\n").unwrap());
+        sut.frame_rate = 30.0;
+        sut.speed = 2.0;
+
+        assert_eq!(sut.get_schedule(), vec![0.0, 0.25])
+    }
+
+    #[test]
+    fn schedule_should_use_timestamps() {
+        let sut = CommandReplayer::new(parse_command_log("10 0 0 0 0 1.5\n25 0 0 0 0 2.0\n").unwrap());
+
+        assert_eq!(sut.get_schedule(), vec![0.0, 0.5])
+    }
+
+    #[test]
+    fn invalid_speed_should_be_clamped() {
+        let mut sut = CommandReplayer::new(parse_command_log("1 0.1 0 0 0 0.0\n2 0 0.2 0 0 0.001\n").unwrap());
+        let mut controller = RecordingController::new();
+        for speed in &[0.0, -1.0, f64::NAN] {
+            sut.speed = *speed;
+            assert_eq!(sut.get_schedule(), vec![0.0, 0.1]);
+        }
+        sut.frame_rate = 0.0;
+
+        assert_eq!(sut.replay(&mut controller), 2)
+    }
+
+    #[test]
+    fn replay_should_send_every_command_and_stop() {
+        let sut = CommandReplayer::new(parse_command_log("1 0.1 0 0 0 0.0\n2 0 0.2 0 0 0.01\n").unwrap());
+        let mut controller = RecordingController::new();
+
+        assert_eq!(sut.replay(&mut controller), 2);
+        assert_eq!(controller.moves, vec![(0.1, 0.0, 0.0, 0.0), (0.0, 0.2, 0.0, 0.0)]);
+        assert!(controller.stopped)
+    }
+
+    #[test]
+    fn stopped_replay_should_send_nothing() {
+        let sut = CommandReplayer::new(parse_command_log("1 0.1 0 0 0\n").unwrap());
+        sut.get_stop_flag().store(true, Ordering::SeqCst);
+        let mut controller = RecordingController::new();
+
+        assert_eq!(sut.replay(&mut controller), 0);
+        assert!(controller.stopped)
+    }
+}
@@ -0,0 +1,50 @@
+use opencv::core::*;
+
+use crate::models::rigid_motion::RigidMotion;
+
+use crate::utils::point_converter::PointConverter;
+use crate::utils::opencv_custom::to_grayscale;
+use crate::utils::optical_flow::{full_mask, find_features, track_features, estimate_rigid_motion};
+
+/// Maximum number of features that are followed between frames.
+const MAX_FEATURES: i32 = 200;
+/// Under this amount of tracked features the registration is not trusted.
+const MIN_FEATURES: usize = 10;
+
+/// Estimates the global motion of the image between consecutive frames (caused by the movement of
+/// the drone), by tracking corner features all over the image with optical flow.
+///
+/// The motion is given in the descartes coordinate system of PointConverter, so the rotation is
+/// around the center of the image.
+pub struct FrameRegistration {
+    prev_gray: Option<Mat>,
+}
+
+impl FrameRegistration {
+    /// Creates a new FrameRegistration, which hasn't seen any frames yet.
+    pub fn new() -> FrameRegistration {
+        FrameRegistration {
+            prev_gray: None,
+        }
+    }
+
+    /// Returns the motion of the image since the last frame given to it. Returns None for the first
+    /// frame, or if not enough features could be tracked.
+    pub fn register(&mut self, img: &Mat, p_c: &PointConverter) -> Option<RigidMotion> {
+        let gray = to_grayscale(img);
+        let motion = self.prev_gray.take().and_then(|prev_gray| {
+            let features = find_features(&prev_gray, &full_mask(&prev_gray), MAX_FEATURES);
+            let (old, new) = track_features(&prev_gray, &gray, &features);
+            if new.len() < MIN_FEATURES {
+                return None;
+            }
+
+            let from = old.iter().map(|p| p_c.convert_from_image_coords_f(&p)).collect::<Vec<(f64, f64)>>();
+            let to = new.iter().map(|p| p_c.convert_from_image_coords_f(&p)).collect::<Vec<(f64, f64)>>();
+            estimate_rigid_motion(&from, &to)
+        });
+        self.prev_gray = Some(gray);
+
+        motion
+    }
+}
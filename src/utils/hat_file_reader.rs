@@ -20,7 +20,7 @@ macro_rules! parse_input {
 /// Where l1, l2 are in range 0 - 100, a1, a2, b1, b2 are in range -127 - 127 and are integers,
 /// hat_size is a double, and video_file_name is a string containing the path to a video file.
 ///
-/// You can use the results to feed in a MockController or a NaiveDetector
+/// You can use the results to feed in a VideoFileSource or a NaiveDetector
 ///
 /// Any other rows after this will not be read.
 pub fn read_file(filename: &str) -> (String, Hat) {
@@ -0,0 +1,97 @@
+/// A value written by the JSON exports (the telemetry and the state records).
+#[derive(Clone, PartialEq, Debug)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    /// NaN and the infinities have no JSON form, they are written as null.
+    Number(f64),
+    Integer(i64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// The fields are written in the given order.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Returns the value as single line JSON.
+    pub fn to_json(&self) -> String {
+        match self {
+            JsonValue::Null => String::from("null"),
+            JsonValue::Bool(value) => value.to_string(),
+            JsonValue::Number(value) if value.is_finite() => value.to_string(),
+            JsonValue::Number(_) => String::from("null"),
+            JsonValue::Integer(value) => value.to_string(),
+            JsonValue::String(value) => escape(value),
+            JsonValue::Array(values) => {
+                let values = values.iter().map(|v| v.to_json()).collect::<Vec<String>>();
+                format!("[{}]", values.join(","))
+            }
+            JsonValue::Object(fields) => {
+                let fields = fields.iter()
+                    .map(|(key, value)| format!("{}:{}", escape(key), value.to_json()))
+                    .collect::<Vec<String>>();
+                format!("{{{}}}", fields.join(","))
+            }
+        }
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> JsonValue {
+        JsonValue::Number(value)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> JsonValue {
+        value.map(|v| v.into()).unwrap_or(JsonValue::Null)
+    }
+}
+
+/// Returns a JSON object with the fields in the given order.
+pub fn json_object(fields: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(fields.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+}
+
+/// Returns the text as a quoted JSON string.
+fn escape(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_object, JsonValue};
+
+    #[test]
+    fn non_finite_numbers_should_be_null() {
+        let sut = JsonValue::Array(vec![
+            JsonValue::Number(1.5), JsonValue::Number(f64::NAN), JsonValue::Number(f64::INFINITY), JsonValue::Number(-f64::INFINITY),
+        ]);
+
+        assert_eq!(sut.to_json(), "[1.5,null,null,null]")
+    }
+
+    #[test]
+    fn object_should_keep_the_order_and_escape_the_strings() {
+        let sut = json_object(vec![
+            ("b", JsonValue::String(String::from("say \"hi\"\n"))),
+            ("a", JsonValue::from(None::<f64>)),
+            ("c", JsonValue::Integer(-3)),
+        ]);
+
+        assert_eq!(sut.to_json(), "{\"b\":\"say \\\"hi\\\"\\n\",\"a\":null,\"c\":-3}")
+    }
+}
@@ -0,0 +1,189 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, Condvar};
+use std::thread;
+use std::time::Duration;
+
+use opencv::core::*;
+use opencv::imgcodecs::{imencode, IMWRITE_JPEG_QUALITY};
+use opencv::types::{VectorOfi32, VectorOfu8};
+
+/// Quality of the JPEG images sent to the clients (0 - 100).
+const JPEG_QUALITY: i32 = 70;
+/// How often the server checks whether it was stopped while waiting for connections.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The latest frame encoded as JPEG, with the number of frames sent so far, so that the clients can
+/// tell whether there is a new one.
+struct SharedFrame {
+    frame_num: u64,
+    jpeg: Vec<u8>,
+    stopped: bool,
+}
+
+/// A minimal HTTP server which streams the frames given to it as MJPEG
+/// (multipart/x-mixed-replace), so they can be watched from a browser (for example on a phone) at
+/// http://<address of the computer>:<port>/
+///
+/// It only listens on the loopback interface unless it is bound to another address (see bind).
+///
+/// Every client is served on its own thread, and always gets the newest frame, slow clients skip
+/// frames instead of slowing down the sender.
+pub struct MjpegServer {
+    address: String,
+    port: u16,
+    shared: Arc<(Mutex<SharedFrame>, Condvar)>,
+}
+
+impl MjpegServer {
+    /// Starts listening on the given port on the loopback interface, so only this computer can
+    /// watch the stream.
+    pub fn new(port: u16) -> std::io::Result<MjpegServer> {
+        MjpegServer::bind("127.0.0.1", port)
+    }
+
+    /// Starts listening on the given address and port ("0.0.0.0" is every network interface).
+    /// Port 0 picks a free port, which can be read with get_port.
+    pub fn bind(address: &str, port: u16) -> std::io::Result<MjpegServer> {
+        let listener = TcpListener::bind((address, port))?;
+        let port = listener.local_addr()?.port();
+        listener.set_nonblocking(true)?;
+        let shared = Arc::new((Mutex::new(SharedFrame { frame_num: 0, jpeg: Vec::new(), stopped: false }), Condvar::new()));
+
+        let server_shared = shared.clone();
+        thread::spawn(move || {
+            accept_clients(listener, server_shared);
+        });
+
+        Ok(MjpegServer {
+            address: String::from(address),
+            port,
+            shared,
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn get_address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the port the server listens on.
+    pub fn get_port(&self) -> u16 {
+        self.port
+    }
+
+    /// Encodes the image as JPEG, and sends it to every connected client. Returns an error if the
+    /// image can't be encoded, the clients keep the previous frame then.
+    pub fn send_frame(&self, img: &Mat) -> opencv::Result<()> {
+        let mut buffer = VectorOfu8::new();
+        let mut params = VectorOfi32::new();
+        params.push(IMWRITE_JPEG_QUALITY);
+        params.push(JPEG_QUALITY);
+        if !imencode(".jpg", img, &mut buffer, &params)? {
+            return Err(opencv::Error::new(StsError, String::from("Couldn't encode the image as JPEG")));
+        }
+        self.send_jpeg(buffer.to_vec());
+        Ok(())
+    }
+
+    /// Sends an image that is already encoded as JPEG to every connected client.
+    pub fn send_jpeg(&self, jpeg: Vec<u8>) {
+        let (lock, condvar) = &*self.shared;
+        let mut frame = lock.lock().unwrap();
+        frame.jpeg = jpeg;
+        frame.frame_num += 1;
+        condvar.notify_all();
+    }
+}
+
+impl Drop for MjpegServer {
+    fn drop(&mut self) {
+        let (lock, condvar) = &*self.shared;
+        lock.lock().unwrap().stopped = true;
+        condvar.notify_all();
+    }
+}
+
+fn accept_clients(listener: TcpListener, shared: Arc<(Mutex<SharedFrame>, Condvar)>) {
+    loop {
+        if shared.0.lock().unwrap().stopped {
+            break;
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let client_shared = shared.clone();
+                thread::spawn(move || {
+                    // The client disconnecting is not an error.
+                    let _ = serve_client(stream, client_shared);
+                });
+            }
+            Err(_) => {
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+fn serve_client(mut stream: TcpStream, shared: Arc<(Mutex<SharedFrame>, Condvar)>) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    // The request itself doesn't matter, every path gets the stream.
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+
+    stream.write_all(b"HTTP/1.0 200 OK\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: close\r\n\
+        Content-Type: multipart/x-mixed-replace; boundary=frame\r\n\r\n")?;
+
+    let (lock, condvar) = &*shared;
+    let mut last_sent = 0;
+    loop {
+        let jpeg = {
+            let mut frame = lock.lock().unwrap();
+            while frame.frame_num == last_sent && !frame.stopped {
+                frame = condvar.wait(frame).unwrap();
+            }
+            if frame.stopped {
+                return Ok(());
+            }
+            last_sent = frame.frame_num;
+            frame.jpeg.clone()
+        };
+
+        stream.write_all(format!("--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n", jpeg.len()).as_bytes())?;
+        stream.write_all(&jpeg)?;
+        stream.write_all(b"\r\n")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    use super::MjpegServer;
+
+    #[test]
+    fn client_should_get_the_multipart_stream() {
+        let sut = MjpegServer::new(0).unwrap();
+        let jpeg = vec![0xff, 0xd8, 1, 2, 3, 0xff, 0xd9];
+        sut.send_jpeg(jpeg.clone());
+
+        let mut stream = TcpStream::connect(("127.0.0.1", sut.get_port())).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        let part = b"--frame\r\nContent-Type: image/jpeg\r\nContent-Length: 7\r\n\r\n";
+        let mut expected = b"HTTP/1.0 200 OK\r\n\
+            Cache-Control: no-cache\r\n\
+            Connection: close\r\n\
+            Content-Type: multipart/x-mixed-replace; boundary=frame\r\n\r\n".to_vec();
+        expected.extend_from_slice(part);
+        expected.extend_from_slice(&jpeg);
+        expected.extend_from_slice(b"\r\n");
+        let mut received = vec![0; expected.len()];
+        stream.read_exact(&mut received).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&received), String::from_utf8_lossy(&expected))
+    }
+}
@@ -1,16 +1,35 @@
+pub mod attitude_compensator;
+pub mod bounded_queue;
+pub mod camera_calibration;
+pub mod camera_model_file;
+pub mod command_log;
+pub mod command_replayer;
+pub mod frame_registration;
 pub mod hat_file_reader;
+pub mod json;
 
 pub mod marker_drawer;
+pub mod mjpeg_server;
 pub mod opencv_custom;
+pub mod optical_flow;
+pub mod playback_controls;
 
 pub mod point_converter;
+pub mod session_recorder;
+pub mod state_exporter;
+pub mod telemetry_publisher;
 
 pub mod text_exporter;
 pub mod video_exporter;
 
+pub use command_replayer::CommandReplayer;
 pub use marker_drawer::MarkerDrawer;
+pub use mjpeg_server::MjpegServer;
+pub use playback_controls::PlaybackControls;
 
 pub use point_converter::PointConverter;
+pub use session_recorder::SessionRecorder;
+pub use state_exporter::StateExporter;
 
 pub use text_exporter::TextExporter;
 pub use video_exporter::VideoExporter;
\ No newline at end of file
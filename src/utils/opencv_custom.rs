@@ -1,7 +1,7 @@
 use std::clone::Clone;
 
 use opencv::core::*;
-use opencv::imgproc::{COLOR_BGR2Lab, cvt_color, LINE_8, line};
+use opencv::imgproc::{COLOR_BGR2Lab, COLOR_BGR2GRAY, cvt_color, LINE_8, line};
 
 use crate::models::lab_color::LabColor;
 
@@ -72,3 +72,10 @@ pub fn get_blue()-> Scalar {
 pub fn get_green()-> Scalar {
     Scalar::new(0.0, 255.0, 0.0, 255.0)
 }
+
+/// Returns a grayscale copy of a BGR image.
+pub fn to_grayscale(img: &Mat) -> Mat {
+    let mut gray = mat_size_of_other_cv_8u(img);
+    cvt_color(img, &mut gray, COLOR_BGR2GRAY, 0).unwrap();
+    gray
+}
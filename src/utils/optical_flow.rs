@@ -0,0 +1,134 @@
+use opencv::core::*;
+use opencv::imgproc::{circle, good_features_to_track, FILLED, LINE_8};
+use opencv::types::{VectorOfPoint2f, VectorOff32, VectorOfu8};
+use opencv::video::{calc_optical_flow_pyr_lk, OPTFLOW_LK_GET_MIN_EIGENVALS};
+
+use crate::models::rigid_motion::RigidMotion;
+
+/// Calculates the rotation and translation that best (least squares) moves the points of `from`
+/// onto the points of `to`. The two slices have to be of the same length, and contain at least two
+/// points, otherwise None is returned.
+pub fn estimate_rigid_motion(from: &[(f64, f64)], to: &[(f64, f64)]) -> Option<RigidMotion> {
+    if from.len() != to.len() || from.len() < 2 {
+        return None;
+    }
+    let n = from.len() as f64;
+    let (fx, fy) = from.iter().fold((0.0, 0.0), |(ax, ay), (x, y)| (ax + x, ay + y));
+    let (tx, ty) = to.iter().fold((0.0, 0.0), |(ax, ay), (x, y)| (ax + x, ay + y));
+    let (fx, fy, tx, ty) = (fx / n, fy / n, tx / n, ty / n);
+
+    let (dot, cross) = from.iter().zip(to.iter())
+        .fold((0.0, 0.0), |(dot, cross), ((px, py), (qx, qy))| {
+            let (px, py, qx, qy) = (px - fx, py - fy, qx - tx, qy - ty);
+            (dot + px * qx + py * qy, cross + px * qy - py * qx)
+        });
+
+    let angle = if dot == 0.0 && cross == 0.0 { 0.0 } else { cross.atan2(dot) };
+    let (sin, cos) = angle.sin_cos();
+
+    Some(RigidMotion {
+        dx: tx - (fx * cos - fy * sin),
+        dy: ty - (fx * sin + fy * cos),
+        angle,
+    })
+}
+
+/// Returns a mask of the size of the given image, which only lets through the circle with the given
+/// center and radius (in image coordinates).
+pub fn circular_mask(img: &Mat, center: Point, radius: i32) -> Mat {
+    let mut mask = Mat::new_rows_cols_with_default(img.rows(), img.cols(), CV_8U, Scalar::all(0.0)).unwrap();
+    circle(&mut mask, center, radius, Scalar::all(255.0), FILLED, LINE_8, 0).unwrap();
+    mask
+}
+
+/// Returns a mask of the size of the given image, which lets through every pixel.
+pub fn full_mask(img: &Mat) -> Mat {
+    Mat::new_rows_cols_with_default(img.rows(), img.cols(), CV_8U, Scalar::all(255.0)).unwrap()
+}
+
+/// Returns at most `max_features` of the strongest corners of a grayscale image, that are inside
+/// the mask.
+pub fn find_features(gray: &Mat, mask: &Mat, max_features: i32) -> VectorOfPoint2f {
+    let mut corners = VectorOfPoint2f::new();
+    good_features_to_track(gray, &mut corners, max_features, 0.01, 3.0, mask, 3, false, 0.04).unwrap();
+
+    corners
+}
+
+/// Tracks the given points from the previous grayscale frame to the current one with pyramidal
+/// Lucas-Kanade optical flow. Returns the points that were successfully tracked, as pairs of old
+/// and new positions.
+pub fn track_features(prev_gray: &Mat, gray: &Mat, points: &VectorOfPoint2f) -> (VectorOfPoint2f, VectorOfPoint2f) {
+    let mut next_points = VectorOfPoint2f::new();
+    let mut status = VectorOfu8::new();
+    let mut err = VectorOff32::new();
+    if points.is_empty() {
+        return (VectorOfPoint2f::new(), next_points);
+    }
+
+    calc_optical_flow_pyr_lk(
+        prev_gray,
+        gray,
+        points,
+        &mut next_points,
+        &mut status,
+        &mut err,
+        Size::new(21, 21),
+        3,
+        TermCriteria::new(TermCriteria_Type::COUNT as i32 + TermCriteria_Type::EPS as i32, 30, 0.01).unwrap(),
+        OPTFLOW_LK_GET_MIN_EIGENVALS,
+        1e-4
+    ).unwrap();
+
+    let mut old_tracked = VectorOfPoint2f::new();
+    let mut new_tracked = VectorOfPoint2f::new();
+    for (i, found) in status.iter().enumerate() {
+        if found == 1 {
+            old_tracked.push(points.get(i).unwrap());
+            new_tracked.push(next_points.get(i).unwrap());
+        }
+    }
+
+    (old_tracked, new_tracked)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::estimate_rigid_motion;
+
+    fn square() -> Vec<(f64, f64)> {
+        vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]
+    }
+
+    #[test]
+    fn too_few_points_should_give_none() {
+        assert!(estimate_rigid_motion(&[(1.0, 1.0)], &[(2.0, 2.0)]).is_none())
+    }
+
+    #[test]
+    fn translation_should_be_found() {
+        let to = square().iter().map(|(x, y)| (x + 3.0, y - 2.0)).collect::<Vec<(f64, f64)>>();
+        let sut = estimate_rigid_motion(&square(), &to).unwrap();
+
+        assert!(sut.angle.abs() < 1e-9 && (sut.dx - 3.0).abs() < 1e-9 && (sut.dy + 2.0).abs() < 1e-9)
+    }
+
+    #[test]
+    fn rotation_should_be_found() {
+        let to = square().iter().map(|(x, y)| (-y, *x)).collect::<Vec<(f64, f64)>>();
+        let sut = estimate_rigid_motion(&square(), &to).unwrap();
+
+        assert!((sut.angle - PI / 2.0).abs() < 1e-9)
+    }
+
+    #[test]
+    fn applied_motion_should_map_onto_target() {
+        let to = square().iter().map(|(x, y)| (-y + 5.0, *x + 1.0)).collect::<Vec<(f64, f64)>>();
+        let sut = estimate_rigid_motion(&square(), &to).unwrap();
+        let (x, y) = sut.apply(10.0, 10.0);
+
+        assert!((x + 5.0).abs() < 1e-9 && (y - 11.0).abs() < 1e-9)
+    }
+}
@@ -0,0 +1,198 @@
+use opencv::core::{Mat, Point, Scalar};
+use opencv::imgproc::{put_text, FONT_HERSHEY_SIMPLEX, LINE_AA};
+
+use crate::step_output::StepOutput;
+
+/// The slowest and the fastest playback speed.
+const MIN_SPEED: f64 = 1.0 / 16.0;
+const MAX_SPEED: f64 = 16.0;
+/// How long (in milliseconds) the keys are waited for at once while the playback is paused.
+pub const PAUSED_DELAY: i32 = 30;
+
+/// What the HatFollower should do after a key was pressed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaybackAction {
+    Continue,
+    /// The frame source should continue from the given frame.
+    Seek(usize),
+    Stop,
+}
+
+/// Pause, single-step, variable speed and jump-to-frame for reviewing recorded videos, controlled
+/// from the keyboard of the highgui window (see the playback_controls setting):
+///
+/// - space: pause / resume
+/// - d or .: step one frame forward (pauses the playback)
+/// - a or ,: step one frame back (pauses the playback)
+/// - + and -: double / halve the speed
+/// - digits and Enter: jump to the typed frame (Backspace deletes a digit)
+/// - q or Esc: stop
+///
+/// Stepping back and jumping need a frame source that can seek (see FrameSource::seek). The
+/// HatFollower resets the filter on a jump, and doesn't send commands, events or telemetry for,
+/// save, record or export the frames visited again.
+pub struct PlaybackControls {
+    paused: bool,
+    speed: f64,
+    /// A single frame was requested while paused.
+    step: bool,
+    typed_frame: String,
+}
+
+impl PlaybackControls {
+    /// Playing at normal speed.
+    pub fn new() -> PlaybackControls {
+        PlaybackControls {
+            paused: false,
+            speed: 1.0,
+            step: false,
+            typed_frame: String::new(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns the speed of the playback compared to the timestamps of the frames.
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Handles a key returned by wait_key (-1 if none was pressed). Position is the number of the
+    /// frame the source will give out next (counted from 0), if it knows it.
+    pub fn handle_key(&mut self, key: i32, position: Option<usize>) -> PlaybackAction {
+        if key < 0 {
+            return PlaybackAction::Continue;
+        }
+        match (key & 0xFF) as u8 {
+            b' ' => self.paused = !self.paused,
+            b'd' | b'.' => {
+                self.paused = true;
+                self.step = true;
+            }
+            b'a' | b',' => {
+                self.paused = true;
+                // The frame before the one shown, which is the one before the next.
+                if let Some(position) = position {
+                    return PlaybackAction::Seek(position.saturating_sub(2));
+                }
+            }
+            b'+' | b'=' => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+            b'-' => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+            c @ b'0'..=b'9' => self.typed_frame.push(c as char),
+            8 => {
+                self.typed_frame.pop();
+            }
+            10 | 13 => {
+                let typed = std::mem::take(&mut self.typed_frame);
+                if let Ok(frame_num) = typed.parse::<usize>() {
+                    return PlaybackAction::Seek(frame_num);
+                }
+            }
+            b'q' | 27 => return PlaybackAction::Stop,
+            _ => {}
+        }
+        PlaybackAction::Continue
+    }
+
+    /// Returns true if the next frame should be read: the playback is running, or a single step
+    /// was requested.
+    pub fn should_read_frame(&mut self) -> bool {
+        let step = self.step;
+        self.step = false;
+        !self.paused || step
+    }
+
+    /// Returns how long (in milliseconds) to wait before the next frame, so the video plays at the
+    /// speed of its timestamps multiplied by the playback speed. dt is the time between the
+    /// current and the previous frame, elapsed is the time spent since the current frame was read
+    /// (both in seconds).
+    pub fn get_delay(&self, dt: f64, elapsed: f64) -> i32 {
+        if self.paused {
+            return PAUSED_DELAY;
+        }
+        (((dt / self.speed - elapsed) * 1000.0).round() as i32).max(1)
+    }
+
+    /// Returns the rows of the overlay: the number of the frame, the state of the playback, and the
+    /// detection and the estimation of the frame.
+    pub fn get_overlay(&self, position: Option<usize>, output: &StepOutput) -> Vec<String> {
+        let frame = position
+            .map(|p| p.saturating_sub(1).to_string())
+            .unwrap_or_else(|| String::from("?"));
+        let mut state = format!("frame {}  speed x{}", frame, self.speed);
+        if self.paused {
+            state.push_str("  paused");
+        }
+        if !self.typed_frame.is_empty() {
+            state.push_str(&format!("  go to {}_", self.typed_frame));
+        }
+        let detected = match &output.detected_position {
+            Some(p) => format!("detected ({:.1}, {:.1})", p.x, p.y),
+            None => String::from("detected -"),
+        };
+        let estimated = match &output.estimated_position {
+            Some(p) => format!("estimated ({:.1}, {:.1})", p.x, p.y),
+            None => String::from("estimated -"),
+        };
+        vec![
+            state,
+            format!("{} angle {} certainty {:.2}", detected,
+                    output.detected_angle.map(|a| format!("{:.2}", a)).unwrap_or_else(|| String::from("-")),
+                    output.detection_certainty),
+            format!("{} angle {:.2} v ({:.1}, {:.1}) certainty {:.2}", estimated, output.estimated_angle,
+                    output.estimated_vx, output.estimated_vy, output.estimation_certainty),
+        ]
+    }
+}
+
+/// Writes the rows to the top left corner of the image.
+pub fn draw_overlay(img: &mut Mat, rows: &[String]) {
+    for (i, row) in rows.iter().enumerate() {
+        put_text(img, row, Point::new(10, 20 + 20 * i as i32), FONT_HERSHEY_SIMPLEX, 0.5,
+                 Scalar::new(255.0, 255.0, 255.0, 0.0), 1, LINE_AA, false).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlaybackAction, PlaybackControls, PAUSED_DELAY};
+
+    #[test]
+    fn stepping_should_pause_and_read_one_frame() {
+        let mut sut = PlaybackControls::new();
+        sut.handle_key(' ' as i32, Some(10));
+        assert!(!sut.should_read_frame());
+
+        sut.handle_key('d' as i32, Some(10));
+        assert!(sut.should_read_frame());
+        assert!(!sut.should_read_frame());
+        assert_eq!(sut.handle_key('a' as i32, Some(10)), PlaybackAction::Seek(8))
+    }
+
+    #[test]
+    fn typed_frame_should_be_jumped_to() {
+        let mut sut = PlaybackControls::new();
+        for key in "1250".bytes() {
+            assert_eq!(sut.handle_key(key as i32, None), PlaybackAction::Continue);
+        }
+        sut.handle_key(8, None);
+
+        assert_eq!(sut.handle_key(13, None), PlaybackAction::Seek(125));
+        assert_eq!(sut.handle_key(13, None), PlaybackAction::Continue)
+    }
+
+    #[test]
+    fn delay_should_follow_the_speed() {
+        let mut sut = PlaybackControls::new();
+        assert_eq!(sut.get_delay(0.04, 0.01), 30);
+
+        sut.handle_key('+' as i32, None);
+        assert_eq!(sut.get_delay(0.04, 0.01), 10);
+        assert_eq!(sut.get_delay(0.04, 0.05), 1);
+
+        sut.handle_key(' ' as i32, None);
+        assert_eq!(sut.get_delay(0.04, 0.0), PAUSED_DELAY)
+    }
+}
@@ -1,7 +1,10 @@
 use opencv::core::*;
 
 use crate::models::geometric_point::GeometricPoint;
+use crate::models::camera_model::CameraModel;
+use crate::models::ground_point::GroundPoint;
 
+#[derive(Clone)]
 pub struct PointConverter {
     width: usize,
     height: usize,
@@ -27,6 +30,16 @@ impl PointConverter {
             height,
         }
     }
+    /// Returns the width of the image in pixels.
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
     /// Returns the center of a descartes coordinate-system (0, 0)
     pub fn get_center(&self) -> GeometricPoint {
         GeometricPoint::new(0, 0)
@@ -53,6 +66,15 @@ impl PointConverter {
             )
     }
 
+    /// Same as convert_from_image_coords, but for subpixel points (for example the ones tracked by
+    /// optical flow). Returns the (x, y) coordinates without rounding them.
+    pub fn convert_from_image_coords_f(&self, point: &Point2f) -> (f64, f64) {
+        (
+            point.x as f64 - (self.width / 2) as f64,
+            ((self.height / 2) as f64 - point.y as f64)
+        )
+    }
+
     /// Takes a point from this descartes coordinate-system and returns one in the coordinate system of an image
     ///
     /// Usage:
@@ -73,6 +95,28 @@ impl PointConverter {
                 (self.height as i32 / 2) - point.y
             )
     }
+
+    /// Takes a point from this descartes coordinate-system and returns where it is on the ground in
+    /// metres, using the given camera model and the altitude of the camera (in metres).
+    ///
+    /// Usage:
+    ///
+    /// ```
+    /// # use rust_drone_follow::utils::point_converter::PointConverter;
+    /// use rust_drone_follow::models::geometric_point::GeometricPoint;
+    /// use rust_drone_follow::models::camera_model::CameraModel;
+    /// // ...
+    /// # fn main() {
+    /// #    let p_c = PointConverter::new(640, 368);
+    ///     let camera_model = CameraModel::new(520.0, 520.0, 320.0, 184.0, [0.0; 5]);
+    ///     let ground = p_c.convert_to_ground(&GeometricPoint::new(52, 0), &camera_model, 2.0);
+    ///     println!("({} m, {} m)", ground.x, ground.y);
+    /// # }
+    /// ```
+    pub fn convert_to_ground(&self, point: &GeometricPoint, camera_model: &CameraModel, altitude: f64) -> GroundPoint {
+        let image_point = self.convert_to_image_coords(point);
+        camera_model.image_to_ground(image_point.x as f64, image_point.y as f64, altitude)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,242 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::{BufReader, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+
+use opencv::core::*;
+use opencv::imgcodecs::imwrite;
+use opencv::types::VectorOfi32;
+
+use crate::{HatFollowerSettings, StepOutput};
+use crate::traits::Controller;
+
+use crate::models::frame::Frame;
+use crate::models::recorded_frame::RecordedFrame;
+use crate::models::telemetry::Telemetry;
+use crate::utils::bounded_queue::{bounded_queue, finish_writer, BoundedSender, BoundedReceiver, QueueOptions};
+
+const MANIFEST_FILE: &str = "manifest.txt";
+const SETTINGS_FILE: &str = "settings.txt";
+const FRAMES_FILE: &str = "frames.txt";
+const IMAGES_DIRECTORY: &str = "frames";
+const VERSION: u32 = 1;
+
+/// The contents of the manifest of a session directory.
+pub struct SessionManifest {
+    pub version: u32,
+    pub video_width: usize,
+    pub video_height: usize,
+    /// The conversion rates and maximum speeds of the original controller.
+    pub kv: f64,
+    pub ka: f64,
+    pub max_speed: f64,
+    pub max_turn_rate: f64,
+    /// Only known if the recording was finished properly.
+    pub frame_count: Option<usize>,
+}
+
+impl SessionManifest {
+    fn to_text(&self) -> String {
+        let mut text = format!(
+            "version {}\nvideo_width {}\nvideo_height {}\nkv {}\nka {}\nmax_speed {}\nmax_turn_rate {}\nsettings {}\nframes {}\nimages {}\n",
+            self.version, self.video_width, self.video_height, self.kv, self.ka, self.max_speed, self.max_turn_rate,
+            SETTINGS_FILE, FRAMES_FILE, IMAGES_DIRECTORY
+        );
+        if let Some(frame_count) = self.frame_count {
+            text.push_str(&format!("frame_count {}\n", frame_count));
+        }
+        text
+    }
+}
+
+/// Saves everything about a flight into a session directory, so it can be replayed later with the
+/// ReplayController:
+///
+/// - manifest.txt: the version of the format, the resolution of the video and the number of frames
+/// - settings.txt: the settings of the HatFollower
+/// - frames.txt: one line per frame (see RecordedFrame::to_line) with its timestamp, the
+///   telemetry, the detection, the estimation and the command sent to the drone
+/// - frames/: the raw images (before any markers were drawn on them) as lossless PNG files
+///
+/// The files are written on a different thread in order not to block the main thread, through a
+/// bounded queue (see the export_queue_options setting). If a frame can't be written, the
+/// recording stops there, and the error can be read with get_errors.
+pub struct SessionRecorder {
+    directory: PathBuf,
+    manifest: SessionManifest,
+    join_handle: Option<thread::JoinHandle<()>>,
+    sender: BoundedSender<(Mat, PathBuf, String)>,
+    error_sender: Sender<String>,
+    error_receiver: Receiver<String>,
+    queue_options: QueueOptions,
+    /// The number of frames written so far.
+    written: Arc<AtomicUsize>,
+}
+
+/// Writes the image and the line of a frame. The line is only written if the image was.
+fn write_frame(frames_file: &mut File, image: &Mat, image_path: &Path, line: &str) -> Result<(), String> {
+    let saved = imwrite(&image_path.to_string_lossy(), image, &VectorOfi32::new())
+        .map_err(|e| format!("Couldn't save {}: {}", image_path.display(), e.message))?;
+    if !saved {
+        return Err(format!("Couldn't save {}", image_path.display()));
+    }
+    frames_file.write_all(line.as_bytes())
+        .map_err(|e| format!("Couldn't write to {}: {}", FRAMES_FILE, e))
+}
+
+fn session_recorder_thread(receiver: BoundedReceiver<(Mat, PathBuf, String)>, mut frames_file: File, errors: Sender<String>, written: Arc<AtomicUsize>) {
+    while let Some((image, image_path, line)) = receiver.recv() {
+        if let Err(e) = write_frame(&mut frames_file, &image, &image_path, &line) {
+            let _ = errors.send(e);
+            break;
+        }
+        written.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl SessionRecorder {
+    /// Creates the session directory (it shouldn't contain another session), and saves the
+    /// manifest (with the resolution of the video and the properties of the controller) and the
+    /// settings into it. The frames wait to be written in a queue set by the export_queue_options of
+    /// the settings.
+    pub fn new<C: Controller>(directory: &str, video_width: usize, video_height: usize, controller: &C, settings: &HatFollowerSettings) -> std::io::Result<SessionRecorder> {
+        let directory = PathBuf::from(directory);
+        fs::create_dir_all(directory.join(IMAGES_DIRECTORY))?;
+
+        let manifest = SessionManifest {
+            version: VERSION,
+            video_width,
+            video_height,
+            kv: controller.get_kv(),
+            ka: controller.get_ka(),
+            max_speed: controller.get_max_speed(),
+            max_turn_rate: controller.get_max_turn_rate(),
+            frame_count: None,
+        };
+        fs::write(directory.join(MANIFEST_FILE), manifest.to_text())?;
+        fs::write(directory.join(SETTINGS_FILE), format!("{:#?}\n", settings))?;
+        let frames_file = File::create(directory.join(FRAMES_FILE))?;
+
+        let queue_options = settings.export_queue_options;
+        let (sender, receiver) = bounded_queue(&queue_options);
+        let (error_sender, error_receiver) = mpsc::channel();
+        let written = Arc::new(AtomicUsize::new(0));
+        let thread_errors = error_sender.clone();
+        let thread_written = Arc::clone(&written);
+        let join_handle = Some(thread::spawn(move || {
+            session_recorder_thread(receiver, frames_file, thread_errors, thread_written);
+        }));
+
+        Ok(SessionRecorder {
+            directory,
+            manifest,
+            join_handle,
+            sender,
+            error_sender,
+            error_receiver,
+            queue_options,
+            written,
+        })
+    }
+
+    /// Returns the session directory.
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Saves the raw frame, the telemetry at the time it was processed, and the output of the
+    /// HatFollower for it.
+    pub fn record(&mut self, frame: &Frame, telemetry: &Telemetry, output: &StepOutput) {
+        let image_file = format!("{}/{:06}.png", IMAGES_DIRECTORY, output.frame_num);
+        let recorded_frame = RecordedFrame {
+            timestamp: frame.timestamp,
+            image_file: image_file.clone(),
+            telemetry: telemetry.clone(),
+            output: output.clone(),
+        };
+        let image = match frame.image.clone() {
+            Ok(image) => image,
+            Err(e) => {
+                let _ = self.error_sender.send(format!("Couldn't copy frame {}: {}", output.frame_num, e.message));
+                return;
+            }
+        };
+        self.sender.send((image, self.directory.join(image_file), format!("{}\n", recorded_frame.to_line())));
+    }
+
+    /// Returns the number of frames dropped because the queue was full.
+    pub fn get_dropped(&self) -> usize {
+        self.sender.get_dropped()
+    }
+
+    /// Returns the errors that happened since the last call.
+    pub fn get_errors(&self) -> Vec<String> {
+        self.error_receiver.try_iter().collect()
+    }
+
+    /// Waits for the frames in the queue to be written (at most for the flush_timeout of the
+    /// queue), saves the number of the written frames into the manifest, and returns the errors
+    /// that weren't read yet. The frames recorded after it are dropped.
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.join_handle.is_none() {
+            return self.get_errors();
+        }
+        let unfinished = finish_writer(&self.sender, &mut self.join_handle, &self.queue_options, "SessionRecorder");
+        let mut errors = self.get_errors();
+        errors.extend(unfinished);
+        self.manifest.frame_count = Some(self.written.load(Ordering::SeqCst));
+        if let Err(e) = fs::write(self.directory.join(MANIFEST_FILE), self.manifest.to_text()) {
+            errors.push(format!("Couldn't finish the manifest of {}: {}", self.directory.display(), e));
+        }
+        errors
+    }
+}
+
+impl Drop for SessionRecorder {
+    /// Finishes the session if it wasn't, the errors are lost then (see finish).
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Reads the manifest of a session directory.
+pub fn read_manifest(directory: &str) -> std::io::Result<SessionManifest> {
+    let text = fs::read_to_string(Path::new(directory).join(MANIFEST_FILE))?;
+    let value = |key: &str| text.lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| String::from(v.trim()));
+    fn required<T: std::str::FromStr>(value: Option<String>, key: &str) -> std::io::Result<T> {
+        value.and_then(|v| v.parse::<T>().ok())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("The manifest doesn't contain a valid {}", key)))
+    }
+
+    Ok(SessionManifest {
+        version: required(value("version"), "version")?,
+        video_width: required(value("video_width"), "video_width")?,
+        video_height: required(value("video_height"), "video_height")?,
+        kv: required(value("kv"), "kv")?,
+        ka: required(value("ka"), "ka")?,
+        max_speed: required(value("max_speed"), "max_speed")?,
+        max_turn_rate: required(value("max_turn_rate"), "max_turn_rate")?,
+        frame_count: value("frame_count").and_then(|v| v.parse::<usize>().ok()),
+    })
+}
+
+/// Reads every recorded frame of a session directory. A session that wasn't finished properly can
+/// end with an incomplete line, which is skipped.
+pub fn read_frames(directory: &str) -> std::io::Result<Vec<RecordedFrame>> {
+    let file = File::open(Path::new(directory).join(FRAMES_FILE))?;
+    let mut frames = Vec::new();
+    for line in BufReader::new(file).lines() {
+        match RecordedFrame::from_line(&line?) {
+            Some(frame) => frames.push(frame),
+            None => break,
+        }
+    }
+    Ok(frames)
+}
@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+
+use crate::models::state_record::StateRecord;
+use crate::utils::bounded_queue::{bounded_queue, finish_writer, BoundedSender, BoundedReceiver, QueueOptions};
+
+/// The file format written by the StateExporter.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StateExportFormat {
+    /// A comment line with the schema version, a header row, and a row per frame.
+    Csv,
+    /// An object with the schema version and the columns, and an object per frame.
+    JsonLines,
+}
+
+/// Saves the StateRecord of every frame into a CSV or JSON Lines file, for analysis in notebooks.
+/// The rows are written on a different thread in order not to block the main thread, through a
+/// bounded queue. If the file can't be written, the export stops there, and the error can be read
+/// with get_errors.
+pub struct StateExporter {
+    filename: String,
+    format: StateExportFormat,
+    join_handle: Option<thread::JoinHandle<()>>,
+    sender: BoundedSender<String>,
+    error_receiver: Receiver<String>,
+    queue_options: QueueOptions,
+}
+
+fn state_exporter_thread(receiver: BoundedReceiver<String>, mut writer: BufWriter<File>, filename: String, errors: Sender<String>) {
+    while let Some(line) = receiver.recv() {
+        if let Err(e) = writer.write_all(line.as_bytes()) {
+            let _ = errors.send(format!("Couldn't write to {}: {}", filename, e));
+            return;
+        }
+    }
+    if let Err(e) = writer.flush() {
+        let _ = errors.send(format!("Couldn't write to {}: {}", filename, e));
+    }
+}
+
+impl StateExporter {
+    /// Creates the file (overwriting it if it exists) and writes the header into it.
+    ///
+    /// Usage:
+    /// ```
+    /// use rust_drone_follow::utils::state_exporter::{StateExporter, StateExportFormat};
+    /// // ...
+    /// # fn main() {
+    ///     let exporter = StateExporter::new("state.csv", StateExportFormat::Csv).unwrap();
+    /// # }
+    /// ```
+    pub fn new(filename: &str, format: StateExportFormat) -> std::io::Result<StateExporter> {
+        StateExporter::with_queue_options(filename, format, QueueOptions::new())
+    }
+
+    /// Creates the file like new, with the given size and policy of the queue of the rows waiting
+    /// to be written.
+    pub fn with_queue_options(filename: &str, format: StateExportFormat, queue_options: QueueOptions) -> std::io::Result<StateExporter> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        let header = match format {
+            StateExportFormat::Csv => StateRecord::csv_header(),
+            StateExportFormat::JsonLines => StateRecord::json_header(),
+        };
+        writeln!(writer, "{}", header)?;
+
+        let (sender, receiver) = bounded_queue(&queue_options);
+        let (error_sender, error_receiver) = mpsc::channel();
+        let thread_filename = String::from(filename);
+        let join_handle = Some(thread::spawn(move || {
+            state_exporter_thread(receiver, writer, thread_filename, error_sender);
+        }));
+
+        Ok(StateExporter {
+            filename: String::from(filename),
+            format,
+            join_handle,
+            sender,
+            error_receiver,
+            queue_options,
+        })
+    }
+
+    /// Returns the name of the file the records are saved to.
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Returns the format of the file.
+    pub fn get_format(&self) -> StateExportFormat {
+        self.format
+    }
+
+    /// Appends the record to the file.
+    pub fn export(&self, record: &StateRecord) {
+        let line = match self.format {
+            StateExportFormat::Csv => record.to_csv(),
+            StateExportFormat::JsonLines => record.to_json(),
+        };
+        self.sender.send(format!("{}\n", line));
+    }
+
+    /// Returns the number of records dropped because the queue was full.
+    pub fn get_dropped(&self) -> usize {
+        self.sender.get_dropped()
+    }
+
+    /// Returns the errors that happened since the last call.
+    pub fn get_errors(&self) -> Vec<String> {
+        self.error_receiver.try_iter().collect()
+    }
+
+    /// Waits for the records in the queue to be written, at most for the flush_timeout of the
+    /// queue, and returns the errors that weren't read yet. The records exported after it are
+    /// dropped.
+    pub fn finish(&mut self) -> Vec<String> {
+        let unfinished = finish_writer(&self.sender, &mut self.join_handle, &self.queue_options, "StateExporter");
+        let mut errors = self.get_errors();
+        errors.extend(unfinished);
+        errors
+    }
+}
+
+impl Drop for StateExporter {
+    /// Finishes the exporter if it wasn't, the errors are lost then (see finish).
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::follower_event::FollowerState;
+    use crate::models::state_record::StateRecord;
+
+    use super::{StateExporter, StateExportFormat};
+
+    fn record(frame_num: usize) -> StateRecord {
+        StateRecord {
+            timestamp: frame_num as f64 / 30.0,
+            frame_num,
+            dt: 1.0 / 30.0,
+            state: FollowerState::Paused,
+            detected_position: None,
+            detected_angle: None,
+            detection_certainty: 0.0,
+            estimated_position: None,
+            estimated_angle: 0.0,
+            estimated_vx: 0.0,
+            estimated_vy: 0.0,
+            estimation_certainty: 0.0,
+            command: None,
+            command_sent: false,
+        }
+    }
+
+    #[test]
+    fn csv_export_should_start_with_the_header() {
+        let filename = std::env::temp_dir().join(format!("state_exporter_test_{}.csv", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        {
+            let sut = StateExporter::new(filename, StateExportFormat::Csv).unwrap();
+            sut.export(&record(1));
+            sut.export(&record(2));
+            assert_eq!(sut.get_dropped(), 0);
+        }
+        let contents = fs::read_to_string(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+        let lines = contents.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "# schema_version 1");
+        assert!(lines[1].starts_with("timestamp,frame_num,") && lines[3].starts_with("0.06666666666666667,2,"))
+    }
+
+    #[test]
+    fn json_lines_export_should_start_with_the_schema() {
+        let filename = std::env::temp_dir().join(format!("state_exporter_test_{}.jsonl", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        {
+            let sut = StateExporter::new(filename, StateExportFormat::JsonLines).unwrap();
+            sut.export(&record(1));
+        }
+        let contents = fs::read_to_string(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+        let lines = contents.lines().collect::<Vec<&str>>();
+
+        assert!(lines[0].starts_with("{\"schema_version\":1,\"columns\":[\"timestamp\","));
+        assert_eq!(lines[1], record(1).to_json())
+    }
+}
@@ -0,0 +1,117 @@
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use crate::models::telemetry_record::TelemetryRecord;
+
+/// The encoding of the records sent by the TelemetryPublisher.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TelemetryFormat {
+    /// One JSON object per datagram.
+    Json,
+    /// The compact binary format of TelemetryRecord::to_bytes.
+    Binary,
+}
+
+/// Sends TelemetryRecords over UDP to a ground station. Sending never blocks, and lost datagrams
+/// are not resent.
+pub struct TelemetryPublisher {
+    socket: UdpSocket,
+    address: String,
+    format: TelemetryFormat,
+}
+
+impl TelemetryPublisher {
+    /// Creates a publisher which sends the records to the given address (for example
+    /// "192.168.1.10:9000").
+    ///
+    /// Usage:
+    /// ```
+    /// use rust_drone_follow::utils::telemetry_publisher::{TelemetryPublisher, TelemetryFormat};
+    /// // ...
+    /// # fn main() {
+    ///     let publisher = TelemetryPublisher::new("127.0.0.1:9000", TelemetryFormat::Json).unwrap();
+    /// # }
+    /// ```
+    pub fn new(address: &str, format: TelemetryFormat) -> std::io::Result<TelemetryPublisher> {
+        let target = address.to_socket_addrs()?.next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "No address to send the telemetry to"))?;
+        let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+        socket.connect(target)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(TelemetryPublisher {
+            socket,
+            address: String::from(address),
+            format,
+        })
+    }
+
+    /// Returns the address the records are sent to.
+    pub fn get_address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the format of the records.
+    pub fn get_format(&self) -> TelemetryFormat {
+        self.format
+    }
+
+    /// Sends the record to the ground station.
+    pub fn publish(&self, record: &TelemetryRecord) -> std::io::Result<()> {
+        let bytes = match self.format {
+            TelemetryFormat::Json => record.to_json().into_bytes(),
+            TelemetryFormat::Binary => record.to_bytes(),
+        };
+        self.socket.send(&bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    use crate::follower_event::FollowerState;
+    use crate::models::geometric_point::GeometricPoint;
+    use crate::models::telemetry_record::TelemetryRecord;
+
+    use super::{TelemetryPublisher, TelemetryFormat};
+
+    fn record() -> TelemetryRecord {
+        TelemetryRecord {
+            timestamp: 0.5,
+            frame_num: 15,
+            detection: Some(GeometricPoint::new(3, 4)),
+            detection_certainty: 0.9,
+            estimate: None,
+            estimated_angle: 0.0,
+            estimated_vx: 0.0,
+            estimated_vy: 0.0,
+            estimation_certainty: 0.0,
+            command: (0.0, 0.0, 0.0, 0.0),
+            state: FollowerState::Paused,
+            battery: Some(0.6),
+        }
+    }
+
+    fn receive(format: TelemetryFormat) -> Vec<u8> {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let sut = TelemetryPublisher::new(&listener.local_addr().unwrap().to_string(), format).unwrap();
+        sut.publish(&record()).unwrap();
+
+        let mut buffer = [0; 2048];
+        let size = listener.recv(&mut buffer).unwrap();
+        buffer[..size].to_vec()
+    }
+
+    #[test]
+    fn binary_record_should_arrive_on_loopback() {
+        assert_eq!(TelemetryRecord::from_bytes(&receive(TelemetryFormat::Binary)), Some(record()))
+    }
+
+    #[test]
+    fn json_record_should_arrive_on_loopback() {
+        assert_eq!(String::from_utf8(receive(TelemetryFormat::Json)).unwrap(), record().to_json())
+    }
+}
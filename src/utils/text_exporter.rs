@@ -1,38 +1,37 @@
 use std::fs::{File};
 use std::io::prelude::*;
 use std::thread;
-use std::sync::mpsc::{self, Sender, Receiver};
 use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender, Receiver};
 
-pub struct TextExporter {
-    join_handle: Option<thread::JoinHandle<()>>,
-    command_sender: Sender<(String, Option<String>)>
-}
+use crate::utils::bounded_queue::{bounded_queue, finish_writer, BoundedSender, BoundedReceiver, QueueOptions};
 
 /// Can be used to save text in the same way VideoExporter does. The saving runs on a different
 /// thread in order not to block the main thread.
-fn text_exporter_thread(rec: Receiver<(String, Option<String>)>) {
-    let mut text_writers: HashMap<String, File> = HashMap::new();
+///
+/// Errors (a file that can't be created or written) don't stop the program: the rest of the rows
+/// of the file are dropped, and the error can be read with get_errors.
+pub struct TextExporter {
+    join_handle: Option<thread::JoinHandle<()>>,
+    row_sender: BoundedSender<(String, String)>,
+    error_receiver: Receiver<String>,
+    queue_options: QueueOptions,
+}
 
-    loop {
-        match rec.recv() {
-            Ok((_, None)) => {
-                break;
-            }
-            Ok((name, text_option)) => {
-                let text = text_option.unwrap();
-                if !text_writers.contains_key(&name) {
-                    let mut file = File::create(&name).unwrap();
-                    file.write(text.as_bytes()).unwrap();
-                    text_writers.insert(name, file);
-                } else {
-                    let file = text_writers.get_mut(&name).unwrap();
-                    file.write(text.as_bytes()).unwrap();
-                }
+fn text_exporter_thread(rec: BoundedReceiver<(String, String)>, errors: Sender<String>) {
+    // None marks a file that couldn't be written.
+    let mut text_writers: HashMap<String, Option<File>> = HashMap::new();
 
-            }
-            Err(_) => {
-                break;
+    while let Some((name, text)) = rec.recv() {
+        let writer = text_writers.entry(name.clone()).or_insert_with(|| {
+            File::create(&name)
+                .map_err(|e| errors.send(format!("Couldn't create {}: {}", name, e)))
+                .ok()
+        });
+        if let Some(file) = writer {
+            if let Err(e) = file.write_all(text.as_bytes()) {
+                let _ = errors.send(format!("Couldn't write to {}: {}", name, e));
+                *writer = None;
             }
         }
     }
@@ -42,27 +41,100 @@ impl TextExporter {
 
     /// Creates a new TextExporter with no managed files.
     pub fn new() -> TextExporter {
-        let (command_sender, receiver) = mpsc::channel();
+        TextExporter::with_queue_options(QueueOptions::new())
+    }
+
+    /// Creates a new TextExporter with no managed files, and the given size and policy of the
+    /// queue of the rows waiting to be written.
+    pub fn with_queue_options(queue_options: QueueOptions) -> TextExporter {
+        let (row_sender, receiver) = bounded_queue(&queue_options);
+        let (error_sender, error_receiver) = mpsc::channel();
         let join_handle = Some(thread::spawn(move || {
-            text_exporter_thread(receiver);
+            text_exporter_thread(receiver, error_sender);
         }));
         TextExporter {
             join_handle,
-            command_sender
+            row_sender,
+            error_receiver,
+            queue_options,
         }
     }
 
     /// Will start writing a file if it isn't managed, otherwise it will append the row to it.
     pub fn save_row(&mut self, text_name: &str, text: String) {
-        self.command_sender.send(
-            (String::from(text_name), Some(text))
-            ).unwrap();
+        self.row_sender.send((String::from(text_name), text));
+    }
+
+    /// Returns the number of rows dropped because the queue was full.
+    pub fn get_dropped(&self) -> usize {
+        self.row_sender.get_dropped()
+    }
+
+    /// Returns the errors that happened since the last call.
+    pub fn get_errors(&self) -> Vec<String> {
+        self.error_receiver.try_iter().collect()
+    }
+
+    /// Waits for the rows in the queue to be written, at most for the flush_timeout of the queue,
+    /// and returns the errors that weren't read yet. The rows saved after it are dropped.
+    pub fn finish(&mut self) -> Vec<String> {
+        let unfinished = finish_writer(&self.row_sender, &mut self.join_handle, &self.queue_options, "TextExporter");
+        let mut errors = self.get_errors();
+        errors.extend(unfinished);
+        errors
     }
 }
 
 impl Drop for TextExporter {
+    /// Finishes the exporter if it wasn't, the errors are lost then (see finish).
     fn drop(&mut self) {
-        self.command_sender.send((String::new(), None)).unwrap();
-        self.join_handle.take().unwrap().join().unwrap();
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::TextExporter;
+
+    #[test]
+    fn rows_should_be_written_when_dropped() {
+        let filename = std::env::temp_dir().join(format!("text_exporter_test_{}.txt", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        {
+            let mut sut = TextExporter::new();
+            for i in 0..100 {
+                sut.save_row(filename, format!("{}\n", i));
+            }
+            assert_eq!(sut.get_dropped(), 0);
+        }
+        let contents = fs::read_to_string(filename).unwrap();
+        fs::remove_file(filename).unwrap();
+
+        assert_eq!(contents.lines().count(), 100)
+    }
+
+    #[test]
+    fn unwritable_file_should_be_reported() {
+        let filename = std::env::temp_dir().join(format!("text_exporter_missing_{}", std::process::id())).join("rows.txt");
+        let mut sut = TextExporter::new();
+        sut.save_row(filename.to_str().unwrap(), String::from("1\n"));
+        sut.save_row(filename.to_str().unwrap(), String::from("2\n"));
+
+        let mut errors = Vec::new();
+        for _ in 0..100 {
+            errors.extend(sut.get_errors());
+            if !errors.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        thread::sleep(Duration::from_millis(20));
+        errors.extend(sut.get_errors());
+
+        assert_eq!(errors.len(), 1)
     }
 }
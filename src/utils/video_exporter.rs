@@ -1,72 +1,327 @@
 use std::thread;
 use std::sync::mpsc::{self, Sender, Receiver};
 use std::collections::HashMap;
+use std::path::Path;
 
 use opencv::core::*;
+use opencv::imgproc::{resize, INTER_AREA};
 use opencv::videoio::*;
 
+use crate::utils::bounded_queue::{bounded_queue, finish_writer, BoundedSender, BoundedReceiver, QueueOptions};
+
+/// How many timestamped frames are used to calculate the frame rate if it isn't given.
+const FPS_SAMPLE_SIZE: usize = 10;
+/// Frame rate used if it isn't given and the frames have no timestamps.
+const DEFAULT_FPS: f64 = 30.0;
+
+/// A frame with its capture time sent to a file.
+type VideoMessage = (String, Mat, Option<f64>);
+
+/// Options of the videos written by the VideoExporter.
+#[derive(Clone, PartialEq, Debug)]
+pub struct VideoExportOptions {
+    /// FourCC code of the codec (for example "FMP4", "MJPG", "XVID" or "avc1").
+    pub codec: String,
+    /// Frames per second of the video. If it is None, it is calculated from the timestamps of the
+    /// first frames (see save_timed_frame), or it is 30 if the frames have no timestamps.
+    pub fps: Option<f64>,
+    /// If it is set, the extension of the file names is replaced with it (for example "avi" or
+    /// "mkv"), since OpenCV chooses the container from the extension.
+    pub container: Option<String>,
+    /// If it is set, the frames are resized to this resolution (width, height).
+    pub resolution: Option<(i32, i32)>,
+    /// The size and policy of the queue of the frames waiting to be written.
+    pub queue: QueueOptions,
+}
+
+impl VideoExportOptions {
+    /// FMP4 codec, with the frame rate calculated from the timestamps.
+    pub fn new() -> VideoExportOptions {
+        VideoExportOptions {
+            codec: String::from("FMP4"),
+            fps: None,
+            container: None,
+            resolution: None,
+            queue: QueueOptions::new(),
+        }
+    }
+}
+
 /// Can be used to export frames to a video file. The saving runs on a different thread in order
 /// not to block the main thread.
+///
+/// Errors (a codec that is not available, a file that can't be written) don't stop the program:
+/// the file is abandoned, and the error can be read with get_errors.
+///
+/// The frames waiting to be written are kept in a bounded queue, when it is full they are handled
+/// according to the policy in the options (see QueueOptions).
 pub struct VideoExporter {
+    options: VideoExportOptions,
+    /// The resolution the frames are resized to, if the one in the options is valid.
+    resolution: Option<Size>,
     join_handle: Option<thread::JoinHandle<()>>,
-    command_sender: Sender<(String, Option<Mat>)>
+    frame_sender: BoundedSender<VideoMessage>,
+    error_sender: Sender<String>,
+    error_receiver: Receiver<String>,
 }
 
-fn video_exporter_thread(rec: Receiver<(String, Option<Mat>)>) {
-    let mut video_writers: HashMap<String, VideoWriter> = HashMap::new();
+/// The state of a video file managed by the exporter.
+enum VideoOutput {
+    /// The first frames are kept until the frame rate can be calculated from their timestamps.
+    Buffering(Vec<(Mat, Option<f64>)>),
+    Writing(VideoWriter),
+    /// Writing the file failed, the rest of its frames are dropped.
+    Failed,
+}
 
-    loop {
-        match rec.recv() {
-            Ok((_, None)) => {
-                break;
-            }
-            Ok((name, image_option)) => {
-                let image = image_option.unwrap();
-                if !video_writers.contains_key(&name) {
-                    let mut vw = VideoWriter::new(name.as_str(),
-                                              VideoWriter::fourcc('F' as i8, 'M' as i8, 'P' as i8, '4' as i8).unwrap(),
-                                              30.0,
-                                              image.size().unwrap(),
-                                              true).unwrap();
-                    vw.write(&image).unwrap();
-                    video_writers.insert(name, vw);
+/// Returns the frame rate of the frames from their timestamps, or None if it can't be calculated.
+fn fps_from_timestamps(timestamps: &[f64]) -> Option<f64> {
+    let duration = timestamps.last()? - timestamps.first()?;
+    if timestamps.len() < 2 || duration <= 0.0 {
+        return None;
+    }
+    Some((timestamps.len() - 1) as f64 / duration)
+}
+
+/// Returns the name of the file with the extension of the container (if it is set).
+fn output_filename(name: &str, container: &Option<String>) -> String {
+    match container {
+        Some(extension) => Path::new(name).with_extension(extension).to_string_lossy().into_owned(),
+        None => String::from(name),
+    }
+}
+
+/// Returns the size the frames should be resized to, or an error if the resolution isn't positive.
+fn parse_resolution(resolution: Option<(i32, i32)>) -> Result<Option<Size>, String> {
+    match resolution {
+        Some((width, height)) if width <= 0 || height <= 0 => Err(format!("Invalid resolution {}x{}, the frames are saved in their original size", width, height)),
+        Some((width, height)) => Ok(Some(Size::new(width, height))),
+        None => Ok(None),
+    }
+}
+
+/// Returns the name of the file with the number before its extension ("out.mp4", 2 -> "out_2.mp4"),
+/// or the name itself for 0.
+pub fn numbered_filename(name: &str, number: usize) -> String {
+    if number == 0 {
+        return String::from(name);
+    }
+    let path = Path::new(name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let numbered = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, number),
+    };
+    path.with_file_name(numbered).to_string_lossy().into_owned()
+}
+
+/// Returns the characters of the FourCC code of the codec, or an error if it isn't 4 ASCII
+/// characters.
+fn parse_fourcc(codec: &str) -> Result<[i8; 4], String> {
+    let c = codec.bytes().collect::<Vec<u8>>();
+    if c.len() != 4 || !codec.is_ascii() {
+        return Err(format!("The codec should be 4 characters long: {}", codec));
+    }
+    Ok([c[0] as i8, c[1] as i8, c[2] as i8, c[3] as i8])
+}
+
+fn open_writer(filename: &str, options: &VideoExportOptions, fps: f64, size: Size) -> Result<VideoWriter, String> {
+    let [c1, c2, c3, c4] = parse_fourcc(&options.codec)?;
+    let fourcc = VideoWriter::fourcc(c1, c2, c3, c4)
+        .map_err(|e| format!("Invalid codec {}: {}", options.codec, e))?;
+    let vw = VideoWriter::new(filename, fourcc, fps, size, true)
+        .map_err(|e| format!("Couldn't create {}: {}", filename, e))?;
+    if !vw.is_opened().unwrap_or(false) {
+        return Err(format!("Couldn't open {} with codec {}", filename, options.codec));
+    }
+    Ok(vw)
+}
+
+/// Opens the file, and writes the buffered frames into it.
+fn start_writing(filename: &str, options: &VideoExportOptions, frames: Vec<(Mat, Option<f64>)>) -> Result<VideoWriter, String> {
+    let timestamps = frames.iter().filter_map(|(_, t)| *t).collect::<Vec<f64>>();
+    let fps = options.fps
+        .or_else(|| fps_from_timestamps(&timestamps))
+        .unwrap_or(DEFAULT_FPS);
+    let size = frames[0].0.size().map_err(|e| e.to_string())?;
+    let mut vw = open_writer(filename, options, fps, size)?;
+    for (image, _) in frames {
+        vw.write(&image).map_err(|e| format!("Couldn't write to {}: {}", filename, e))?;
+    }
+    Ok(vw)
+}
+
+fn video_exporter_thread(rec: BoundedReceiver<VideoMessage>, options: VideoExportOptions, errors: Sender<String>) {
+    let mut video_outputs: HashMap<String, VideoOutput> = HashMap::new();
+
+    while let Some((name, image, timestamp)) = rec.recv() {
+        let output = video_outputs.entry(name.clone()).or_insert_with(|| VideoOutput::Buffering(Vec::new()));
+        let result = match output {
+            VideoOutput::Buffering(frames) => {
+                frames.push((image, timestamp));
+                // The frame rate is known if it was given, or if the frames have no timestamps.
+                if options.fps.is_some() || timestamp.is_none() || frames.len() >= FPS_SAMPLE_SIZE {
+                    let frames = std::mem::take(frames);
+                    start_writing(&name, &options, frames).map(|vw| *output = VideoOutput::Writing(vw))
                 } else {
-                    let vw = video_writers.get_mut(&name).unwrap();
-                    vw.write(&image).unwrap();
+                    Ok(())
                 }
-
             }
-            Err(_) => {
-                break;
+            VideoOutput::Writing(vw) => vw.write(&image)
+                .map_err(|e| format!("Couldn't write to {}: {}", name, e)),
+            VideoOutput::Failed => Ok(()),
+        };
+        if let Err(e) = result {
+            let _ = errors.send(e);
+            video_outputs.insert(name, VideoOutput::Failed);
+        }
+    }
+
+    // Videos shorter than the sample are written with the frame rate of the frames they have.
+    for (name, output) in video_outputs {
+        if let VideoOutput::Buffering(frames) = output {
+            if let Err(e) = start_writing(&name, &options, frames) {
+                let _ = errors.send(e);
             }
         }
     }
 }
 
 impl VideoExporter {
-    /// Creates a new VideoExporter with no managed files.
+    /// Creates a new VideoExporter with no managed files, and the default options.
     pub fn new() -> VideoExporter {
-        let (command_sender, receiver) = mpsc::channel();
+        VideoExporter::with_options(VideoExportOptions::new())
+    }
+
+    /// Creates a new VideoExporter with no managed files, which writes every file with the given
+    /// options. An invalid resolution is reported as an error (see get_errors), and the frames
+    /// aren't resized.
+    pub fn with_options(options: VideoExportOptions) -> VideoExporter {
+        let (frame_sender, receiver) = bounded_queue(&options.queue);
+        let (error_sender, error_receiver) = mpsc::channel();
+        let resolution = parse_resolution(options.resolution).unwrap_or_else(|e| {
+            let _ = error_sender.send(e);
+            None
+        });
+        let thread_options = options.clone();
+        let thread_error_sender = error_sender.clone();
         let join_handle = Some(thread::spawn(move || {
-            video_exporter_thread(receiver);
+            video_exporter_thread(receiver, thread_options, thread_error_sender);
         }));
         VideoExporter {
+            options,
+            resolution,
             join_handle,
-            command_sender
+            frame_sender,
+            error_sender,
+            error_receiver,
         }
     }
 
+    /// Returns the options the files are written with.
+    pub fn get_options(&self) -> &VideoExportOptions {
+        &self.options
+    }
+
     /// Will start writing a file if it isn't managed, otherwise it will append the frame to it.
     pub fn save_frame(&mut self, video_name: &str, img: &Mat) {
-        self.command_sender.send(
-            (String::from(video_name), Some(img.clone().unwrap()))
-            ).unwrap();
+        self.send(video_name, img, None);
+    }
+
+    /// Same as save_frame, but the capture time of the frame (in seconds) is used to calculate the
+    /// frame rate, if it isn't set in the options.
+    pub fn save_timed_frame(&mut self, video_name: &str, img: &Mat, timestamp: f64) {
+        self.send(video_name, img, Some(timestamp));
+    }
+
+    /// Copies (or resizes) the frame, and puts it into the queue. If it can't be copied, the error
+    /// is reported and the frame is dropped.
+    fn send(&mut self, video_name: &str, img: &Mat, timestamp: Option<f64>) {
+        let image = match self.resolution {
+            Some(size) => Mat::default().and_then(|mut resized| {
+                resize(img, &mut resized, size, 0.0, 0.0, INTER_AREA).map(|_| resized)
+            }),
+            None => img.clone(),
+        };
+        match image {
+            Ok(image) => {
+                let filename = output_filename(video_name, &self.options.container);
+                self.frame_sender.send((filename, image, timestamp));
+            }
+            Err(e) => {
+                let _ = self.error_sender.send(format!("Couldn't copy the frame for {}: {}", video_name, e));
+            }
+        }
+    }
+
+    /// Returns the number of frames dropped because the queue was full.
+    pub fn get_dropped(&self) -> usize {
+        self.frame_sender.get_dropped()
+    }
+
+    /// Returns the errors that happened since the last call.
+    pub fn get_errors(&self) -> Vec<String> {
+        self.error_receiver.try_iter().collect()
+    }
+
+    /// Waits for the frames in the queue to be written, at most for the flush_timeout of the
+    /// queue, and returns the errors that weren't read yet. The frames saved after it are dropped.
+    pub fn finish(&mut self) -> Vec<String> {
+        let unfinished = finish_writer(&self.frame_sender, &mut self.join_handle, &self.options.queue, "VideoExporter");
+        let mut errors = self.get_errors();
+        errors.extend(unfinished);
+        errors
     }
 }
 
 impl Drop for VideoExporter {
+    /// Finishes the exporter if it wasn't, the errors are lost then (see finish).
     fn drop(&mut self) {
-        self.command_sender.send((String::new(), None)).unwrap();
-        self.join_handle.take().unwrap().join().unwrap();
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fps_from_timestamps, output_filename, numbered_filename, parse_fourcc, VideoExporter, VideoExportOptions};
+
+    #[test]
+    fn fps_should_be_calculated_from_timestamps() {
+        let sut = fps_from_timestamps(&[1.0, 1.0 + 1.0 / 15.0, 1.0 + 2.0 / 15.0, 1.2]).unwrap();
+
+        assert!((sut - 15.0).abs() < 1e-9)
+    }
+
+    #[test]
+    fn fps_should_be_unknown_from_a_single_frame() {
+        assert!(fps_from_timestamps(&[1.0]).is_none())
+    }
+
+    #[test]
+    fn container_should_replace_the_extension() {
+        assert_eq!(output_filename("videos/out.mp4", &Some(String::from("avi"))), "videos/out.avi");
+        assert_eq!(output_filename("videos/out.mp4", &None), "videos/out.mp4")
+    }
+
+    #[test]
+    fn number_should_go_before_the_extension() {
+        assert_eq!(numbered_filename("videos/out.mp4", 0), "videos/out.mp4");
+        assert_eq!(numbered_filename("videos/out.mp4", 2), "videos/out_2.mp4");
+        assert_eq!(numbered_filename("out", 1), "out_1")
+    }
+
+    #[test]
+    fn codec_should_be_split_into_characters() {
+        assert_eq!(parse_fourcc("MJPG"), Ok([b'M' as i8, b'J' as i8, b'P' as i8, b'G' as i8]));
+        assert!(parse_fourcc("H264X").is_err())
+    }
+
+    #[test]
+    fn invalid_resolution_should_be_reported() {
+        let mut options = VideoExportOptions::new();
+        options.resolution = Some((0, 720));
+        let sut = VideoExporter::with_options(options);
+
+        assert_eq!(sut.get_errors().len(), 1)
     }
 }